@@ -1,27 +1,205 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::convert::Infallible;
-use std::path::Path;
-use std::sync::Arc;
-use std::time::Instant;
+use std::ffi::CString;
+use std::fmt;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use anyhow::Context;
-use bytes::Bytes;
+use bytes::{Buf, Bytes};
+use clap::Parser;
 use http_body_util::{BodyExt, Full};
-use hyper::body::Incoming;
+use hyper::body::{Body, Incoming};
+use hyper::header::{ALLOW, CONTENT_LENGTH, CONTENT_TYPE, HOST, HeaderValue, RETRY_AFTER};
 use hyper::server::conn::http1;
 use hyper::service::service_fn;
 use hyper::{Method, Request, Response, StatusCode};
 use hyper_util::rt::TokioIo;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use socket2::{Domain, SockAddr, Socket, Type};
 use tokio::net::UnixListener;
-use tokio::sync::RwLock;
+use tokio::signal::unix::{SignalKind, signal};
+use tokio::sync::{Notify, RwLock, RwLockWriteGuard, Semaphore, mpsc};
 
 const DEFAULT_SOCKET_PATH: &str = "/tmp/timer.sock";
+const DEFAULT_BACKLOG: i32 = 512;
+const DEFAULT_QUEUE_TIMEOUT_MS: u64 = 1000;
+const DEFAULT_RETRY_AFTER_SECS: u64 = 1;
+/// Mutations queued for the mirror beyond this are dropped with a warning.
+const MIRROR_QUEUE_LEN: usize = 1024;
+const MIRROR_TIMEOUT: Duration = Duration::from_secs(5);
+/// Marks requests replayed by `--mirror`, so a peer never mirrors them on and
+/// two servers mirroring each other do not loop.
+const MIRRORED_HEADER: &str = "x-timersock-mirrored";
+const DEFAULT_TIMER: &str = "default";
+/// Caps how much of a request hyper buffers while parsing it; requests whose
+/// headers do not fit are rejected with `431 Request Header Fields Too Large`.
+const MAX_BUF_SIZE: usize = 64 * 1024;
+/// Milliseconds from receiving a request to building its response.
+const RESPONSE_TIME_HEADER: &str = "x-response-time-ms";
+/// Bumped whenever `TimerSnapshot` changes in a way old clients would misread.
+const SNAPSHOT_SCHEMA_VERSION: u32 = 1;
+const TICK: Duration = Duration::from_secs(1);
+const DEFAULT_WAIT_SECS: u64 = 30;
+const MAX_WAIT_SECS: u64 = 300;
+
+/// Every route and the method it answers to, consulted when a request does not
+/// match so a wrong method gets `405` instead of `404`. Keep in sync with
+/// `route_request`.
+const ROUTE_METHODS: &[(&str, &str)] = &[
+    ("/healthz", "GET"),
+    ("/openmetrics", "GET"),
+    ("/config", "GET"),
+    ("/debug/stats", "GET"),
+    ("/time_left", "GET"),
+    ("/wait", "GET"),
+    ("/timers", "GET"),
+    ("/start", "POST"),
+    ("/interval", "POST"),
+    ("/schedule", "GET"),
+    ("/schedule", "POST"),
+    ("/cancel_schedule", "POST"),
+    ("/message", "GET"),
+    ("/message", "POST"),
+    ("/pause", "POST"),
+    ("/resume", "POST"),
+    ("/toggle", "POST"),
+    ("/extend", "POST"),
+    ("/reduce", "POST"),
+    // `/timer/<name>` itself.
+    ("", "GET"),
+    ("", "POST"),
+];
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "timersock",
+    about = "UNIX socket server that stores timer state"
+)]
+struct Args {
+    #[arg(long, env = "TIMER_SOCK", default_value = DEFAULT_SOCKET_PATH)]
+    socket: PathBuf,
+
+    /// Maximum number of pending connections queued by the listener.
+    #[arg(long, default_value_t = DEFAULT_BACKLOG, value_parser = clap::value_parser!(i32).range(1..))]
+    backlog: i32,
+
+    /// Leave the socket file in place on graceful shutdown.
+    #[arg(long)]
+    keep_socket: bool,
+
+    /// Keep running countdowns going past zero and report how far overdue
+    /// they are in `overtime_secs`.
+    #[arg(long)]
+    allow_overtime: bool,
+
+    /// Refuse to `reduce` a running timer below this many seconds unless the
+    /// request sets `force`. `0` disables the floor.
+    #[arg(long, default_value_t = 0)]
+    min_secs: u64,
+
+    /// Write a message to syslog (facility `daemon`, level `notice`) each time
+    /// a timer completes.
+    #[arg(long)]
+    syslog: bool,
+
+    /// Collect request, lock-wait and connection counters for
+    /// `GET /debug/stats`.
+    #[arg(long)]
+    stats: bool,
+
+    /// Write the server's PID to this file on startup and remove it on
+    /// graceful shutdown.
+    #[arg(long)]
+    pid_file: Option<PathBuf>,
+
+    /// Serve at most this many connections at once; further ones wait for a
+    /// free slot.
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    max_connections: Option<u32>,
+
+    /// How long, in milliseconds, a connection waits for a slot under
+    /// `--max-connections` before getting `503 Service Unavailable`.
+    #[arg(long, default_value_t = DEFAULT_QUEUE_TIMEOUT_MS)]
+    queue_timeout: u64,
+
+    /// Reject `GET` and `HEAD` requests that carry a body with `400`.
+    #[arg(long)]
+    strict_methods: bool,
+
+    /// Seconds sent in `Retry-After` with those `503` responses.
+    #[arg(long, default_value_t = DEFAULT_RETRY_AFTER_SECS)]
+    retry_after: u64,
+
+    /// Socket of a peer timersock to replay every successful `POST` to, on a
+    /// best-effort basis.
+    #[arg(long)]
+    mirror: Option<PathBuf>,
+}
+
+/// The server's effective settings, served by `GET /config`. Timers copy the
+/// ones that apply to them when created.
+#[derive(Debug, Clone, Serialize)]
+struct ServerConfig {
+    socket_path: PathBuf,
+    backlog: i32,
+    keep_socket: bool,
+    allow_overtime: bool,
+    min_secs: u64,
+    syslog: bool,
+    stats: bool,
+    pid_file: Option<PathBuf>,
+    max_connections: Option<u32>,
+    queue_timeout_ms: u64,
+    retry_after_secs: u64,
+    strict_methods: bool,
+    mirror: Option<PathBuf>,
+    max_header_bytes: usize,
+    /// Granularity of the countdown; `time_left_secs` only moves in steps of
+    /// this size.
+    tick_secs: u64,
+}
 
 #[derive(Debug, Clone)]
 struct TimerState {
     time_left_secs: u64,
     running: bool,
+    original_duration_secs: u64,
+    allow_overtime: bool,
+    overtime_secs: u64,
+    min_secs: u64,
+    /// Whole seconds spent running since the last `start`, overtime included.
+    total_running_secs: u64,
+    completed_at: Option<Instant>,
+    /// Set on completion until `take_completion` reports it.
+    unreported_completion: bool,
     updated_at: Instant,
+    current_step: Option<IntervalStep>,
+    steps: VecDeque<IntervalStep>,
+    total_reps: u32,
+    /// Pending scheduled actions, ordered by when they are due.
+    schedules: Vec<Scheduled>,
+    /// Pause once when the countdown reaches this many seconds left. Cleared
+    /// when it fires and on every `start`.
+    pause_at: Option<u64>,
+    /// Text to announce on completion; empty means the default wording.
+    /// Survives restarts of the timer.
+    message: String,
+    /// Wall-clock audit times, separate from the monotonic `updated_at`
+    /// used for the countdown.
+    created_at: SystemTime,
+    /// Last change made by a request (or a scheduled action), not by the
+    /// countdown itself.
+    modified_at: SystemTime,
+    /// When the current session began: creation, then every `start`.
+    session_started_at: SystemTime,
+    /// Last snapshot handed out. Every mutation goes through `refresh` or
+    /// `start`, and both drop it.
+    cached_snapshot: Option<TimerSnapshot>,
 }
 
 impl Default for TimerState {
@@ -29,53 +207,291 @@ impl Default for TimerState {
         Self {
             time_left_secs: 0,
             running: false,
+            original_duration_secs: 0,
+            allow_overtime: false,
+            overtime_secs: 0,
+            min_secs: 0,
+            total_running_secs: 0,
+            completed_at: None,
+            unreported_completion: false,
             updated_at: Instant::now(),
+            current_step: None,
+            steps: VecDeque::new(),
+            total_reps: 0,
+            schedules: Vec::new(),
+            pause_at: None,
+            message: String::new(),
+            created_at: SystemTime::now(),
+            modified_at: SystemTime::now(),
+            session_started_at: SystemTime::now(),
+            cached_snapshot: None,
         }
     }
 }
 
 impl TimerState {
+    fn new(config: &ServerConfig) -> Self {
+        Self {
+            allow_overtime: config.allow_overtime,
+            min_secs: config.min_secs,
+            ..Self::default()
+        }
+    }
+
+    /// A timer can count as long as it has time left or is already overdue.
+    fn can_run(&self) -> bool {
+        self.time_left_secs > 0 || self.overtime_secs > 0
+    }
+
+    /// Records the first completion since the timer was last cleared.
+    fn mark_completed(&mut self, at: Instant) {
+        if self.completed_at.is_none() {
+            self.completed_at = Some(at);
+            self.unreported_completion = true;
+        }
+    }
+
+    /// Returns whether the timer completed since the last call, so each
+    /// completion is reported exactly once.
+    fn take_completion(&mut self) -> bool {
+        std::mem::take(&mut self.unreported_completion)
+    }
+
+    /// Brings the timer up to date, first replaying any scheduled actions that
+    /// fell due since the last refresh at the instant they were due.
     fn refresh(&mut self) {
+        self.cached_snapshot = None;
+        let now = Instant::now();
+        loop {
+            let schedule_due = self
+                .schedules
+                .first()
+                .map(|scheduled| scheduled.at)
+                .filter(|at| *at <= now);
+            let pause_due = self.pause_at_due().filter(|at| *at <= now);
+            match (schedule_due, pause_due) {
+                (_, Some(at)) if schedule_due.is_none_or(|scheduled_at| at < scheduled_at) => {
+                    self.advance_to(at);
+                    self.pause_at = None;
+                    self.running = false;
+                    self.touch();
+                }
+                (Some(_), _) => {
+                    let scheduled = self.schedules.remove(0);
+                    self.advance_to(scheduled.at);
+                    self.apply_scheduled(scheduled.action);
+                }
+                (None, _) => break,
+            }
+        }
+        self.advance_to(now);
+    }
+
+    /// When a running countdown reaches `pause_at`; a timer already at or
+    /// below it pauses straight away.
+    fn pause_at_due(&self) -> Option<Instant> {
+        let threshold = self.pause_at.filter(|_| self.running)?;
+        let until = self.time_left_secs.saturating_sub(threshold);
+        Some(self.updated_at + Duration::from_secs(until))
+    }
+
+    fn advance_to(&mut self, now: Instant) {
         if !self.running {
-            self.updated_at = Instant::now();
+            self.updated_at = now;
             return;
         }
 
-        let now = Instant::now();
-        let elapsed = now.duration_since(self.updated_at).as_secs();
+        let mut elapsed = now.duration_since(self.updated_at).as_secs();
         if elapsed == 0 {
             return;
         }
+        let total_elapsed = elapsed;
 
-        self.time_left_secs = self.time_left_secs.saturating_sub(elapsed);
-        if self.time_left_secs == 0 {
-            self.running = false;
+        // Refreshes can be far apart, so each finish instant is derived from
+        // the last update rather than using `now`. Queued interval steps pick
+        // up from where the previous one ended.
+        while elapsed >= self.time_left_secs {
+            let finished_at = self.updated_at + Duration::from_secs(self.time_left_secs);
+            elapsed -= self.time_left_secs;
+            match self.steps.pop_front() {
+                Some(step) => {
+                    self.time_left_secs = step.seconds;
+                    self.original_duration_secs = step.seconds;
+                    self.current_step = Some(step);
+                    self.updated_at = finished_at;
+                }
+                None if self.allow_overtime => {
+                    // Completion still happens once, at the zero crossing;
+                    // only the overshoot past zero counts as overtime.
+                    self.time_left_secs = 0;
+                    self.mark_completed(finished_at);
+                    self.overtime_secs += elapsed;
+                    self.total_running_secs += total_elapsed;
+                    self.updated_at = now;
+                    return;
+                }
+                None => {
+                    self.time_left_secs = 0;
+                    self.running = false;
+                    self.mark_completed(finished_at);
+                    self.total_running_secs += total_elapsed - elapsed;
+                    self.updated_at = now;
+                    return;
+                }
+            }
         }
+
+        self.time_left_secs -= elapsed;
+        self.total_running_secs += total_elapsed;
         self.updated_at = now;
     }
 
+    /// Applies `action` as of `updated_at`; the caller advances the timer to
+    /// the scheduled instant first.
+    fn apply_scheduled(&mut self, action: ScheduledAction) {
+        match action {
+            ScheduledAction::Pause => self.running = false,
+            ScheduledAction::Resume => {
+                if !self.running && self.can_run() {
+                    self.running = true;
+                }
+            }
+            ScheduledAction::Stop => {
+                self.time_left_secs = 0;
+                self.overtime_secs = 0;
+                self.running = false;
+                self.completed_at = None;
+                self.current_step = None;
+                self.steps.clear();
+                self.total_reps = 0;
+            }
+        }
+    }
+
+    /// Whole seconds since the current session began; zero if the wall
+    /// clock has gone backwards since.
+    fn session_age_secs(&self) -> u64 {
+        self.session_started_at
+            .elapsed()
+            .map(|age| age.as_secs())
+            .unwrap_or(0)
+    }
+
+    /// Records a change to `modified_at`.
+    fn touch(&mut self) {
+        self.modified_at = SystemTime::now();
+        self.cached_snapshot = None;
+    }
+
+    /// Times in the past are due immediately and applied right away.
+    fn schedule(&mut self, action: ScheduledAction, at_unix: u64) {
+        self.touch();
+        let scheduled = Scheduled {
+            action,
+            at_unix,
+            at: unix_to_instant(at_unix),
+        };
+        let index = self
+            .schedules
+            .partition_point(|pending| pending.at <= scheduled.at);
+        self.schedules.insert(index, scheduled);
+        self.refresh();
+    }
+
+    /// Drops pending actions matching `action` (all when `None`) and returns
+    /// them. Actions that are already due are applied first, not cancelled.
+    fn cancel_schedules(&mut self, action: Option<ScheduledAction>) -> Vec<Scheduled> {
+        self.refresh();
+        self.touch();
+        let (cancelled, kept) = std::mem::take(&mut self.schedules)
+            .into_iter()
+            .partition(|scheduled| action.is_none_or(|action| scheduled.action == action));
+        self.schedules = kept;
+        cancelled
+    }
+
+    /// Starting with zero seconds is treated as a timer that finished
+    /// instantly: it is not running and is marked completed.
     fn start(&mut self, seconds: u64) {
+        self.touch();
+        self.session_started_at = self.modified_at;
+        self.pause_at = None;
         self.time_left_secs = seconds;
+        self.overtime_secs = 0;
+        self.total_running_secs = 0;
+        self.original_duration_secs = seconds;
         self.running = seconds > 0;
         self.updated_at = Instant::now();
+        self.completed_at = None;
+        self.unreported_completion = false;
+        if seconds == 0 {
+            self.mark_completed(self.updated_at);
+        }
+        self.current_step = None;
+        self.steps.clear();
+        self.total_reps = 0;
+    }
+
+    /// Runs `steps` back to back, starting with the first one.
+    fn start_interval(&mut self, steps: Vec<IntervalStep>, total_reps: u32) {
+        let mut steps = VecDeque::from(steps);
+        let first = steps.pop_front();
+        self.start(first.as_ref().map_or(0, |step| step.seconds));
+        self.current_step = first;
+        self.steps = steps;
+        self.total_reps = total_reps;
     }
 
     fn pause(&mut self) {
         self.refresh();
+        self.touch();
         self.running = false;
     }
 
     fn resume(&mut self) {
         self.refresh();
-        if self.time_left_secs > 0 {
+        self.touch();
+        if self.can_run() {
             self.running = true;
             self.updated_at = Instant::now();
         }
     }
 
-    fn extend(&mut self, seconds: u64) {
+    /// Returns the seconds left before and after extending.
+    fn extend(&mut self, seconds: u64) -> (u64, u64) {
         self.refresh();
-        self.time_left_secs = self.time_left_secs.saturating_add(seconds);
+        self.touch();
+        let before = self.time_left_secs;
+        self.time_left_secs = before.saturating_add(seconds);
+        if self.time_left_secs > 0 {
+            self.completed_at = None;
+            self.overtime_secs = 0;
+        }
+        (before, self.time_left_secs)
+    }
+
+    /// Reducing a timer to zero stops it and marks it completed.
+    /// Returns how many seconds were actually taken off, which is less than
+    /// `seconds` when the timer runs out first or hits the `min_secs` floor.
+    /// The floor only guards running timers and never adds time to one
+    /// already below it.
+    fn reduce(&mut self, seconds: u64, force: bool) -> u64 {
+        self.refresh();
+        self.touch();
+        if self.time_left_secs == 0 {
+            return 0;
+        }
+
+        let before = self.time_left_secs;
+        let reduced = before.saturating_sub(seconds);
+        let floor = self.min_secs.min(before);
+        let below_floor = self.running && !force && reduced < floor;
+        self.time_left_secs = if below_floor { floor } else { reduced };
+        if self.time_left_secs == 0 {
+            self.running = false;
+            self.mark_completed(Instant::now());
+        }
+        before - self.time_left_secs
     }
 
     fn toggle(&mut self) {
@@ -87,15 +503,113 @@ impl TimerState {
         }
     }
 
+    /// A paused or finished timer cannot change without a mutation or a due
+    /// schedule, and a running one only changes once a full tick has passed
+    /// since the last update, so the cached snapshot is reused until then.
+    /// Only the session age is recomputed on every call.
     fn snapshot(&mut self) -> TimerSnapshot {
+        if let Some(snapshot) = &self.cached_snapshot
+            && (!self.running || self.updated_at.elapsed() < TICK)
+            && self
+                .schedules
+                .first()
+                .is_none_or(|scheduled| scheduled.at > Instant::now())
+        {
+            return TimerSnapshot {
+                session_age_secs: self.session_age_secs(),
+                ..snapshot.clone()
+            };
+        }
+
         self.refresh();
-        TimerSnapshot {
+        let snapshot = TimerSnapshot {
+            schema_version: SNAPSHOT_SCHEMA_VERSION,
             time_left_secs: self.time_left_secs,
             time_left_hms: format_hms(self.time_left_secs),
-            alt: state_alt(self.time_left_secs, self.running),
+            alt: if self.overtime_secs > 0 {
+                "overtime"
+            } else {
+                state_alt(self.time_left_secs, self.running)
+            },
             running: self.running,
-        }
+            original_duration_secs: self.original_duration_secs,
+            overtime_secs: self.overtime_secs,
+            total_running_secs: self.total_running_secs,
+            message: self.message.clone(),
+            completed: self.completed_at.is_some(),
+            completed_at_unix: self.completed_at.map(instant_to_unix),
+            interval: self.current_step.as_ref().map(|step| IntervalProgress {
+                label: step.label.clone(),
+                rep: step.rep,
+                total_reps: self.total_reps,
+            }),
+            deadline_unix: (self.running && self.time_left_secs > 0).then(|| {
+                instant_to_unix(self.updated_at + Duration::from_secs(self.time_left_secs))
+            }),
+            created_at_unix: system_time_to_unix(self.created_at),
+            modified_at_unix: system_time_to_unix(self.modified_at),
+            session_age_secs: self.session_age_secs(),
+            pause_at: self.pause_at,
+        };
+        self.cached_snapshot = Some(snapshot.clone());
+        snapshot
+    }
+}
+
+/// Maps a unix timestamp onto the monotonic clock; past times map to now.
+fn unix_to_instant(at_unix: u64) -> Instant {
+    let target = UNIX_EPOCH + Duration::from_secs(at_unix);
+    let now = Instant::now();
+    match target.duration_since(SystemTime::now()) {
+        Ok(ahead) => now + ahead,
+        Err(_) => now,
+    }
+}
+
+fn system_time_to_unix(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0)
+}
+
+fn instant_to_unix(instant: Instant) -> u64 {
+    let now = Instant::now();
+    let system_now = SystemTime::now();
+    let time = if instant <= now {
+        system_now.checked_sub(now - instant).unwrap_or(UNIX_EPOCH)
+    } else {
+        system_now + (instant - now)
+    };
+    system_time_to_unix(time)
+}
+
+/// Inverse of `format_hms`, also accepting `MM:SS`. Minutes and seconds must
+/// be below 60; hours are unbounded.
+fn parse_hms(text: &str) -> Result<u64, String> {
+    let invalid = || format!("`{text}` is not a valid HH:MM:SS or MM:SS time");
+    let fields = text
+        .split(':')
+        .map(|field| {
+            if field.is_empty() || !field.bytes().all(|byte| byte.is_ascii_digit()) {
+                return Err(invalid());
+            }
+            field.parse::<u64>().map_err(|_| invalid())
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let (hours, minutes, seconds) = match fields[..] {
+        [hours, minutes, seconds] => (hours, minutes, seconds),
+        [minutes, seconds] => (0, minutes, seconds),
+        _ => return Err(invalid()),
+    };
+    if minutes >= 60 || seconds >= 60 {
+        return Err(format!(
+            "`{text}` is out of range: minutes and seconds must be below 60"
+        ));
     }
+    hours
+        .checked_mul(3600)
+        .and_then(|secs| secs.checked_add(minutes * 60 + seconds))
+        .ok_or_else(|| format!("`{text}` is too large"))
 }
 
 fn format_hms(total_secs: u64) -> String {
@@ -115,20 +629,272 @@ fn state_alt(time_left_secs: u64, running: bool) -> &'static str {
     }
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 struct TimerSnapshot {
+    schema_version: u32,
     time_left_secs: u64,
     time_left_hms: String,
     alt: &'static str,
     running: bool,
+    original_duration_secs: u64,
+    overtime_secs: u64,
+    total_running_secs: u64,
+    message: String,
+    completed: bool,
+    completed_at_unix: Option<u64>,
+    deadline_unix: Option<u64>,
+    interval: Option<IntervalProgress>,
+    created_at_unix: u64,
+    modified_at_unix: u64,
+    session_age_secs: u64,
+    pause_at: Option<u64>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+struct IntervalProgress {
+    label: String,
+    rep: u32,
+    total_reps: u32,
+}
+
+#[derive(Debug, PartialEq, Eq, Deserialize)]
 struct SecondsBody {
+    seconds: Option<u64>,
+    /// `HH:MM:SS` or `MM:SS`; takes precedence over `seconds`.
+    hms: Option<String>,
+}
+
+impl SecondsBody {
+    /// Errors carry the status and message for `error_response`.
+    fn to_seconds(&self) -> Result<u64, (StatusCode, String)> {
+        match (&self.hms, self.seconds) {
+            (Some(hms), _) => parse_hms(hms).map_err(|err| (StatusCode::UNPROCESSABLE_ENTITY, err)),
+            (None, Some(seconds)) => Ok(seconds),
+            (None, None) => Err((
+                StatusCode::BAD_REQUEST,
+                r#"expected JSON like {"seconds": 300} or {"hms": "00:05:00"}"#.to_string(),
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct WaitResponse {
+    #[serde(flatten)]
+    snapshot: TimerSnapshot,
+    reached: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct StartBody {
+    #[serde(flatten)]
+    duration: SecondsBody,
+    /// Refuse with `409 Conflict` instead of restarting a running timer.
+    #[serde(default)]
+    no_clobber: bool,
+    /// Pause once when this many seconds are left; must be below the
+    /// duration.
+    pause_at: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExtendBody {
+    #[serde(flatten)]
+    duration: SecondsBody,
+    /// Restart a timer that has already run out, counting down the added time.
+    #[serde(default)]
+    resume: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct MessageBody {
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReduceBody {
     seconds: u64,
+    #[serde(default)]
+    force: bool,
+}
+
+/// Body of `POST /timer/<name>`, which runs one of the body-less actions.
+#[derive(Debug, Deserialize)]
+struct ActionBody {
+    action: TimerAction,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum TimerAction {
+    Pause,
+    Resume,
+    Toggle,
+}
+
+impl TimerAction {
+    fn apply(self, timer: &mut TimerState) {
+        match self {
+            Self::Pause => timer.pause(),
+            Self::Resume => timer.resume(),
+            Self::Toggle => timer.toggle(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ReduceResponse {
+    #[serde(flatten)]
+    snapshot: TimerSnapshot,
+    reduced_by_secs: u64,
+    /// Less than the requested amount was taken off.
+    clamped: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct IntervalStep {
+    label: String,
+    seconds: u64,
+    rep: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ScheduledAction {
+    Pause,
+    Resume,
+    Stop,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct Scheduled {
+    action: ScheduledAction,
+    at_unix: u64,
+    #[serde(skip)]
+    at: Instant,
+}
+
+#[derive(Debug, Deserialize)]
+struct ScheduleBody {
+    action: ScheduledAction,
+    at_unix: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct CancelScheduleBody {
+    /// Only cancel actions of this kind; all of them when absent.
+    #[serde(default)]
+    action: Option<ScheduledAction>,
+}
+
+#[derive(Debug, Serialize)]
+struct CancelScheduleResponse {
+    cancelled: Vec<Scheduled>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IntervalBody {
+    steps: Vec<IntervalStep>,
+    total_reps: u32,
+    /// Same as for `POST /start`.
+    #[serde(default)]
+    no_clobber: bool,
+}
+
+/// Snapshot entry returned by the multi-timer poll; unknown names are only
+/// reported when the caller asks for them with `missing=include`.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum PollEntry {
+    Found(Box<TimerSnapshot>),
+    Missing { missing: bool },
+}
+
+type Timers = HashMap<String, TimerState>;
+
+struct AppState {
+    /// Every handler takes the write lock once and builds its response before
+    /// releasing it, so each request observes the state left by the previous
+    /// one and never a half-applied change.
+    timers: RwLock<Timers>,
+    config: ServerConfig,
+    /// Only present with `--stats`, so the counters cost nothing otherwise.
+    stats: Option<Stats>,
+    /// Signalled after every request so the idle watchdog rechecks the timers.
+    handled_request: Notify,
+    /// Successful mutations waiting to be replayed to the `--mirror` peer.
+    mirror: Option<mpsc::Sender<MirroredRequest>>,
+}
+
+impl AppState {
+    /// Takes the timers lock, recording how long it took when stats are on.
+    async fn lock_timers(&self) -> RwLockWriteGuard<'_, Timers> {
+        let Some(stats) = &self.stats else {
+            return self.timers.write().await;
+        };
+        let started = Instant::now();
+        let guard = self.timers.write().await;
+        let waited = u64::try_from(started.elapsed().as_nanos()).unwrap_or(u64::MAX);
+        stats.lock_acquisitions.fetch_add(1, Ordering::Relaxed);
+        stats.lock_wait_nanos.fetch_add(waited, Ordering::Relaxed);
+        stats
+            .lock_wait_max_nanos
+            .fetch_max(waited, Ordering::Relaxed);
+        guard
+    }
 }
 
-type SharedState = Arc<RwLock<TimerState>>;
+type SharedState = Arc<AppState>;
+
+#[derive(Debug, Default)]
+struct Stats {
+    requests: AtomicU64,
+    lock_acquisitions: AtomicU64,
+    lock_wait_nanos: AtomicU64,
+    lock_wait_max_nanos: AtomicU64,
+    connections_active: AtomicU64,
+    connections_max: AtomicU64,
+    completions: AtomicU64,
+}
+
+impl Stats {
+    fn connection_opened(&self) {
+        let active = self.connections_active.fetch_add(1, Ordering::Relaxed) + 1;
+        self.connections_max.fetch_max(active, Ordering::Relaxed);
+    }
+
+    fn connection_closed(&self) {
+        self.connections_active.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    fn report(&self) -> StatsReport {
+        let lock_acquisitions = self.lock_acquisitions.load(Ordering::Relaxed);
+        let lock_wait_nanos = self.lock_wait_nanos.load(Ordering::Relaxed);
+        StatsReport {
+            requests_total: self.requests.load(Ordering::Relaxed),
+            lock_acquisitions,
+            lock_wait_avg_micros: lock_wait_nanos
+                .checked_div(lock_acquisitions)
+                .unwrap_or_default() as f64
+                / 1000.0,
+            lock_wait_max_micros: self.lock_wait_max_nanos.load(Ordering::Relaxed) as f64 / 1000.0,
+            connections_active: self.connections_active.load(Ordering::Relaxed),
+            connections_max: self.connections_max.load(Ordering::Relaxed),
+            completions_total: self.completions.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct StatsReport {
+    requests_total: u64,
+    lock_acquisitions: u64,
+    lock_wait_avg_micros: f64,
+    lock_wait_max_micros: f64,
+    connections_active: u64,
+    connections_max: u64,
+    completions_total: u64,
+}
 
 type RespBody = Full<Bytes>;
 
@@ -143,17 +909,53 @@ fn json_response<T: Serialize>(status: StatusCode, value: &T) -> Response<RespBo
             .status(StatusCode::INTERNAL_SERVER_ERROR)
             .header("content-type", "application/json")
             .body(Full::new(Bytes::from(format!(
-                "{{\"error\":\"serialization failure: {err}\"}}"
+                "{{\"error\":\"serialization failure: {err}\",\"error_code\":\"internal\"}}"
             ))))
             .expect("building error response should not fail"),
     }
 }
 
+/// Coarse error category sent as `error_code`, so clients can branch on it
+/// without matching the human-readable message.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum ErrorCode {
+    NotFound,
+    StateConflict,
+    ValidationError,
+    Internal,
+}
+
+impl ErrorCode {
+    fn for_status(status: StatusCode) -> Self {
+        match status {
+            StatusCode::NOT_FOUND => Self::NotFound,
+            StatusCode::CONFLICT => Self::StateConflict,
+            status if status.is_client_error() => Self::ValidationError,
+            _ => Self::Internal,
+        }
+    }
+}
+
 fn error_response(status: StatusCode, message: &str) -> Response<RespBody> {
-    json_response(status, &serde_json::json!({ "error": message }))
+    json_response(
+        status,
+        &serde_json::json!({ "error": message, "error_code": ErrorCode::for_status(status) }),
+    )
+}
+
+fn text_response(status: StatusCode, content_type: &str, body: String) -> Response<RespBody> {
+    Response::builder()
+        .status(status)
+        .header("content-type", content_type)
+        .body(Full::new(Bytes::from(body)))
+        .expect("building text response should not fail")
 }
 
-async fn parse_seconds_body(req: Request<Incoming>) -> Result<SecondsBody, Response<RespBody>> {
+async fn parse_json_body<T: DeserializeOwned>(
+    req: Request<impl Body<Error: fmt::Display>>,
+    example: &str,
+) -> Result<T, Response<RespBody>> {
     let bytes = req
         .into_body()
         .collect()
@@ -161,95 +963,1028 @@ async fn parse_seconds_body(req: Request<Incoming>) -> Result<SecondsBody, Respo
         .map_err(|err| error_response(StatusCode::BAD_REQUEST, &format!("invalid body: {err}")))?
         .to_bytes();
 
-    serde_json::from_slice::<SecondsBody>(&bytes).map_err(|err| {
+    serde_json::from_slice::<T>(&bytes).map_err(|err| {
         error_response(
             StatusCode::BAD_REQUEST,
-            &format!("expected JSON like {{\"seconds\": 300}}: {err}"),
+            &format!("expected JSON like {example}: {err}"),
         )
     })
 }
 
-async fn handle_request(
+/// Reads frames until one carries data; `Ok(false)` if the body ends empty,
+/// which covers `Content-Length: 0` and an empty chunked body alike.
+async fn has_body_data<B: Body + Unpin>(body: &mut B) -> Result<bool, B::Error> {
+    while let Some(frame) = body.frame().await {
+        if frame?.data_ref().is_some_and(Buf::has_remaining) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+fn query_param<'a>(query: Option<&'a str>, key: &str) -> Option<&'a str> {
+    query?.split('&').find_map(|pair| {
+        let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+        (name == key).then_some(value)
+    })
+}
+
+/// Decodes `%XX` escapes in a timer name or query value; `None` for a
+/// malformed escape or a result that is not UTF-8.
+fn percent_decode(text: &str) -> Option<String> {
+    let mut decoded = Vec::with_capacity(text.len());
+    let mut rest = text.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = tail
+                .get(..2)
+                .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))?;
+            decoded.push(u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            decoded.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+/// Splits `/timer/<name>/<route>` into the timer name and route; any other
+/// path targets the default timer.
+fn split_timer_path(path: &str) -> Option<(&str, &str)> {
+    match path.strip_prefix("/timer/") {
+        Some(rest) => {
+            // A bare `/timer/<name>` has the empty route.
+            let (name, route) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+            (!name.is_empty()).then_some((name, route))
+        }
+        None => Some((DEFAULT_TIMER, path)),
+    }
+}
+
+async fn update_timer(
+    state: &SharedState,
+    name: &str,
+    update: impl FnOnce(&mut TimerState),
+) -> Response<RespBody> {
+    let mut guard = state.lock_timers().await;
+    match guard.get_mut(name) {
+        Some(timer) => {
+            update(timer);
+            let snapshot = timer.snapshot();
+            json_response(StatusCode::OK, &snapshot)
+        }
+        None => error_response(StatusCode::NOT_FOUND, &format!("timer {name:?} not found")),
+    }
+}
+
+/// Long-polls until the timer has fewer than `below` seconds left. The lock is
+/// only held while checking, and checks line up with the countdown's ticks.
+async fn wait_below(
+    state: &SharedState,
+    name: &str,
+    below: u64,
+    timeout: Duration,
+) -> Response<RespBody> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        let next_check = {
+            let mut guard = state.lock_timers().await;
+            let Some(timer) = guard.get_mut(name) else {
+                return error_response(StatusCode::NOT_FOUND, &format!("timer {name:?} not found"));
+            };
+            let snapshot = timer.snapshot();
+            let now = Instant::now();
+            let reached = snapshot.time_left_secs < below;
+            if reached || now >= deadline {
+                return json_response(StatusCode::OK, &WaitResponse { snapshot, reached });
+            }
+            (timer.updated_at + TICK).clamp(now, deadline)
+        };
+        tokio::time::sleep_until(next_check.into()).await;
+    }
+}
+
+/// Like `update_timer`, but creates the timer if it does not exist yet. With
+/// `no_clobber`, a running timer is left alone and `409 Conflict` returned.
+async fn upsert_timer(
+    state: &SharedState,
+    name: &str,
+    no_clobber: bool,
+    update: impl FnOnce(&mut TimerState),
+) -> Response<RespBody> {
+    let mut guard = state.lock_timers().await;
+    let timer = guard
+        .entry(name.to_string())
+        .or_insert_with(|| TimerState::new(&state.config));
+    timer.refresh();
+    // Restarting clears the completion flag, so report it before it is lost.
+    report_completion(state, name, timer);
+    if no_clobber && timer.running {
+        return error_response(
+            StatusCode::CONFLICT,
+            &format!("timer {name:?} is already running"),
+        );
+    }
+    update(timer);
+    let snapshot = timer.snapshot();
+    json_response(StatusCode::OK, &snapshot)
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn push_gauge(
+    out: &mut String,
+    metric: &str,
+    unit: Option<&str>,
+    help: &str,
+    snapshots: &[(String, TimerSnapshot)],
+    value: impl Fn(&TimerSnapshot) -> u64,
+) {
+    out.push_str(&format!("# TYPE {metric} gauge\n"));
+    if let Some(unit) = unit {
+        out.push_str(&format!("# UNIT {metric} {unit}\n"));
+    }
+    out.push_str(&format!("# HELP {metric} {help}\n"));
+    for (name, snapshot) in snapshots {
+        out.push_str(&format!(
+            "{metric}{{timer=\"{name}\"}} {}\n",
+            value(snapshot)
+        ));
+    }
+}
+
+/// Renders one gauge family per metric with a sample for every timer, in the
+/// OpenMetrics text format.
+async fn render_openmetrics(state: &SharedState) -> String {
+    let mut guard = state.lock_timers().await;
+    let mut snapshots: Vec<(String, TimerSnapshot)> = guard
+        .iter_mut()
+        .map(|(name, timer)| (escape_label_value(name), timer.snapshot()))
+        .collect();
+    snapshots.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut out = String::new();
+    push_gauge(
+        &mut out,
+        "timer_time_left_seconds",
+        Some("seconds"),
+        "Seconds remaining on the timer.",
+        &snapshots,
+        |snapshot| snapshot.time_left_secs,
+    );
+    push_gauge(
+        &mut out,
+        "timer_original_duration_seconds",
+        Some("seconds"),
+        "Duration the timer was last started with.",
+        &snapshots,
+        |snapshot| snapshot.original_duration_secs,
+    );
+    push_gauge(
+        &mut out,
+        "timer_running",
+        None,
+        "Whether the timer is counting down.",
+        &snapshots,
+        |snapshot| u64::from(snapshot.running),
+    );
+    out.push_str("# EOF\n");
+    out
+}
+
+async fn poll_timers(
+    state: &SharedState,
+    names: &str,
+    include_missing: bool,
+) -> Response<RespBody> {
+    let mut guard = state.lock_timers().await;
+    let mut entries = BTreeMap::new();
+    for name in names.split(',').filter(|name| !name.is_empty()) {
+        match guard.get_mut(name) {
+            Some(timer) => {
+                entries.insert(
+                    name.to_string(),
+                    PollEntry::Found(Box::new(timer.snapshot())),
+                );
+            }
+            None if include_missing => {
+                entries.insert(name.to_string(), PollEntry::Missing { missing: true });
+            }
+            None => {}
+        }
+    }
+    json_response(StatusCode::OK, &entries)
+}
+
+/// Every `GET` route also answers `HEAD`.
+fn allowed_methods(route: &str) -> Vec<&'static str> {
+    ROUTE_METHODS
+        .iter()
+        .filter(|(path, _)| *path == route)
+        .flat_map(|(_, method)| match *method {
+            "GET" => vec!["GET", "HEAD"],
+            method => vec![method],
+        })
+        .collect()
+}
+
+/// Turns a `GET` response into its `HEAD` counterpart: same status and
+/// headers, including the length the body would have had, but no body.
+fn strip_body(response: Response<RespBody>) -> Response<RespBody> {
+    let (mut parts, body) = response.into_parts();
+    if let Some(length) = body.size_hint().exact() {
+        parts
+            .headers
+            .insert(CONTENT_LENGTH, HeaderValue::from(length));
+    }
+    Response::from_parts(parts, Full::new(Bytes::new()))
+}
+
+/// Handles the request, then queues successful `POST`s for `--mirror`. Those
+/// have their body buffered up front so it can be replayed.
+async fn serve_request(
     req: Request<Incoming>,
     state: SharedState,
 ) -> Result<Response<RespBody>, Infallible> {
-    let path = req.uri().path();
-    let method = req.method().clone();
+    let Some(mirror) = state
+        .mirror
+        .as_ref()
+        .filter(|_| req.method() == Method::POST && !req.headers().contains_key(MIRRORED_HEADER))
+    else {
+        return handle_request(req, state).await;
+    };
+    let mirror = mirror.clone();
 
-    let response = match (method, path) {
-        (Method::GET, "/time_left") => {
-            let mut guard = state.write().await;
-            let snapshot = guard.snapshot();
-            json_response(StatusCode::OK, &snapshot)
+    let (parts, body) = req.into_parts();
+    let body = match body.collect().await {
+        Ok(collected) => collected.to_bytes(),
+        Err(err) => {
+            return Ok(error_response(
+                StatusCode::BAD_REQUEST,
+                &format!("invalid body: {err}"),
+            ));
         }
-        (Method::POST, "/start") => match parse_seconds_body(req).await {
-            Ok(body) => {
-                let mut guard = state.write().await;
-                guard.start(body.seconds);
-                let snapshot = guard.snapshot();
-                json_response(StatusCode::OK, &snapshot)
+    };
+    let path = parts
+        .uri
+        .path_and_query()
+        .map_or_else(|| parts.uri.path().to_string(), ToString::to_string);
+    let response =
+        handle_request(Request::from_parts(parts, Full::new(body.clone())), state).await?;
+    if response.status().is_success()
+        && let Err(err) = mirror.try_send(MirroredRequest { path, body })
+    {
+        eprintln!("warning: not mirroring request: {err}");
+    }
+    Ok(response)
+}
+
+async fn handle_request<B: Body<Error: fmt::Display> + Unpin>(
+    req: Request<B>,
+    state: SharedState,
+) -> Result<Response<RespBody>, Infallible> {
+    let received = Instant::now();
+    if let Some(stats) = &state.stats {
+        stats.requests.fetch_add(1, Ordering::Relaxed);
+    }
+    let head = req.method() == Method::HEAD;
+    let method = if head {
+        Method::GET
+    } else {
+        req.method().clone()
+    };
+    let path = req.uri().path().to_string();
+    let query = req.uri().query().map(str::to_string);
+
+    let response = match split_timer_path(&path) {
+        None => error_response(StatusCode::NOT_FOUND, "route not found"),
+        Some((name, route)) => match percent_decode(name) {
+            Some(name) => route_request(req, &state, method, &name, route, query.as_deref()).await,
+            None => error_response(
+                StatusCode::BAD_REQUEST,
+                "invalid percent-encoding in timer name",
+            ),
+        },
+    };
+
+    let mut response = if head { strip_body(response) } else { response };
+    set_response_time(&mut response, received);
+    state.handled_request.notify_one();
+    Ok(response)
+}
+
+/// Milliseconds, with three decimals, since the request was `received`.
+fn set_response_time(response: &mut Response<RespBody>, received: Instant) {
+    let millis = received.elapsed().as_secs_f64() * 1000.0;
+    if let Ok(value) = HeaderValue::from_str(&format!("{millis:.3}")) {
+        response.headers_mut().insert(RESPONSE_TIME_HEADER, value);
+    }
+}
+
+/// Dispatches on the method (with `HEAD` already mapped to `GET`) and the
+/// route within the timer called `name`.
+async fn route_request<B: Body<Error: fmt::Display> + Unpin>(
+    mut req: Request<B>,
+    state: &SharedState,
+    method: Method,
+    name: &str,
+    route: &str,
+    query: Option<&str>,
+) -> Response<RespBody> {
+    if method == Method::GET && state.config.strict_methods {
+        match has_body_data(req.body_mut()).await {
+            Ok(false) => {}
+            Ok(true) => {
+                return error_response(
+                    StatusCode::BAD_REQUEST,
+                    "GET and HEAD requests must not have a body",
+                );
+            }
+            Err(err) => {
+                return error_response(StatusCode::BAD_REQUEST, &format!("invalid body: {err}"));
             }
-            Err(err) => err,
+        }
+    }
+    match (method, route) {
+        (Method::GET, "/healthz") => {
+            json_response(StatusCode::OK, &serde_json::json!({ "status": "ok" }))
+        }
+        // Built once at startup and never locked.
+        (Method::GET, "/config") => json_response(StatusCode::OK, &state.config),
+        (Method::GET, "/debug/stats") => match &state.stats {
+            Some(stats) => json_response(StatusCode::OK, &stats.report()),
+            None => error_response(
+                StatusCode::NOT_FOUND,
+                "stats are disabled; start timersock with --stats",
+            ),
         },
-        (Method::POST, "/pause") => {
-            let mut guard = state.write().await;
-            guard.pause();
-            let snapshot = guard.snapshot();
-            json_response(StatusCode::OK, &snapshot)
+        (Method::GET, "/openmetrics") => text_response(
+            StatusCode::OK,
+            "application/openmetrics-text; version=1.0.0; charset=utf-8",
+            render_openmetrics(state).await,
+        ),
+        (Method::GET, "/timers") => {
+            let mut guard = state.lock_timers().await;
+            let snapshots: BTreeMap<&str, TimerSnapshot> = guard
+                .iter_mut()
+                .map(|(name, timer)| (name.as_str(), timer.snapshot()))
+                .collect();
+            json_response(StatusCode::OK, &snapshots)
         }
-        (Method::POST, "/resume") => {
-            let mut guard = state.write().await;
-            guard.resume();
-            let snapshot = guard.snapshot();
-            json_response(StatusCode::OK, &snapshot)
+        (Method::GET, "/wait") => {
+            let below = query_param(query, "below").map(str::parse::<u64>);
+            let timeout = query_param(query, "timeout").map_or(
+                Ok(Duration::from_secs(DEFAULT_WAIT_SECS)),
+                |secs| {
+                    let secs = secs.parse::<f64>().map_err(|_| ())?;
+                    Duration::try_from_secs_f64(secs).map_err(|_| ())
+                },
+            );
+            match (below, timeout) {
+                (Some(Ok(below)), Ok(timeout)) if below > 0 => {
+                    let timeout = timeout.min(Duration::from_secs(MAX_WAIT_SECS));
+                    wait_below(state, name, below, timeout).await
+                }
+                _ => error_response(
+                    StatusCode::BAD_REQUEST,
+                    "expected a query like ?below=60&timeout=30 with below > 0",
+                ),
+            }
         }
-        (Method::POST, "/toggle") => {
-            let mut guard = state.write().await;
-            guard.toggle();
-            let snapshot = guard.snapshot();
-            json_response(StatusCode::OK, &snapshot)
+        (Method::GET, "") => update_timer(state, name, |_| {}).await,
+        (Method::POST, "") => {
+            match parse_json_body::<ActionBody>(req, r#"{"action": "pause"}"#).await {
+                Ok(body) => update_timer(state, name, |timer| body.action.apply(timer)).await,
+                Err(err) => err,
+            }
         }
-        (Method::POST, "/extend") => match parse_seconds_body(req).await {
-            Ok(body) => {
-                let mut guard = state.write().await;
-                guard.extend(body.seconds);
-                let snapshot = guard.snapshot();
-                json_response(StatusCode::OK, &snapshot)
+        (Method::GET, "/time_left") => match query_param(query, "names").map(percent_decode) {
+            Some(Some(names)) => {
+                let include_missing = query_param(query, "missing") == Some("include");
+                poll_timers(state, &names, include_missing).await
+            }
+            Some(None) => {
+                error_response(StatusCode::BAD_REQUEST, "invalid percent-encoding in names")
             }
-            Err(err) => err,
+            None => update_timer(state, name, |_| {}).await,
         },
-        _ => error_response(StatusCode::NOT_FOUND, "route not found"),
+        (Method::POST, "/start") => {
+            let example = r#"{"seconds": 300, "no_clobber": false, "pause_at": null}"#;
+            let parsed = parse_json_body::<StartBody>(req, example).await;
+            match parsed.map(|body| (body.duration.to_seconds(), body)) {
+                Ok((Ok(seconds), body))
+                    if body.pause_at.is_some_and(|at| at == 0 || at >= seconds) =>
+                {
+                    error_response(
+                        StatusCode::BAD_REQUEST,
+                        "pause_at must be at least 1 and below the duration",
+                    )
+                }
+                Ok((Ok(seconds), body)) => {
+                    upsert_timer(state, name, body.no_clobber, |timer| {
+                        timer.start(seconds);
+                        timer.pause_at = body.pause_at;
+                    })
+                    .await
+                }
+                Ok((Err((status, message)), _)) => error_response(status, &message),
+                Err(err) => err,
+            }
+        }
+        (Method::POST, "/interval") => {
+            let example =
+                r#"{"steps": [{"label": "work", "seconds": 30, "rep": 1}], "total_reps": 1}"#;
+            match parse_json_body::<IntervalBody>(req, example).await {
+                Ok(body) if body.steps.is_empty() => {
+                    error_response(StatusCode::BAD_REQUEST, "interval needs at least one step")
+                }
+                Ok(body) => {
+                    upsert_timer(state, name, body.no_clobber, |timer| {
+                        timer.start_interval(body.steps, body.total_reps)
+                    })
+                    .await
+                }
+                Err(err) => err,
+            }
+        }
+        (Method::GET, "/schedule") => {
+            let mut guard = state.lock_timers().await;
+            match guard.get_mut(name) {
+                Some(timer) => {
+                    timer.refresh();
+                    json_response(StatusCode::OK, &timer.schedules)
+                }
+                None => error_response(StatusCode::NOT_FOUND, &format!("timer {name:?} not found")),
+            }
+        }
+        (Method::POST, "/schedule") => {
+            let example = r#"{"action": "pause", "at_unix": 1700000000}"#;
+            match parse_json_body::<ScheduleBody>(req, example).await {
+                Ok(body) => {
+                    update_timer(state, name, |timer| {
+                        timer.schedule(body.action, body.at_unix)
+                    })
+                    .await
+                }
+                Err(err) => err,
+            }
+        }
+        (Method::POST, "/cancel_schedule") => {
+            let example = r#"{"action": "pause"}"#;
+            match parse_json_body::<CancelScheduleBody>(req, example).await {
+                Ok(body) => {
+                    let mut guard = state.lock_timers().await;
+                    match guard.get_mut(name) {
+                        Some(timer) => {
+                            let cancelled = timer.cancel_schedules(body.action);
+                            json_response(StatusCode::OK, &CancelScheduleResponse { cancelled })
+                        }
+                        None => error_response(
+                            StatusCode::NOT_FOUND,
+                            &format!("timer {name:?} not found"),
+                        ),
+                    }
+                }
+                Err(err) => err,
+            }
+        }
+        (Method::GET, "/message") => {
+            let guard = state.lock_timers().await;
+            match guard.get(name) {
+                Some(timer) => json_response(
+                    StatusCode::OK,
+                    &MessageBody {
+                        message: timer.message.clone(),
+                    },
+                ),
+                None => error_response(StatusCode::NOT_FOUND, &format!("timer {name:?} not found")),
+            }
+        }
+        (Method::POST, "/message") => {
+            let example = r#"{"message": "Tea is ready"}"#;
+            match parse_json_body::<MessageBody>(req, example).await {
+                Ok(body) => {
+                    update_timer(state, name, |timer| {
+                        timer.message = body.message;
+                        timer.touch();
+                    })
+                    .await
+                }
+                Err(err) => err,
+            }
+        }
+        (Method::POST, "/pause") => update_timer(state, name, TimerState::pause).await,
+        (Method::POST, "/resume") => update_timer(state, name, TimerState::resume).await,
+        (Method::POST, "/toggle") => update_timer(state, name, TimerState::toggle).await,
+        (Method::POST, "/extend") => {
+            let example = r#"{"seconds": 300, "resume": false}"#;
+            let parsed = parse_json_body::<ExtendBody>(req, example).await;
+            match parsed.map(|body| (body.duration.to_seconds(), body.resume)) {
+                Ok((Ok(seconds), resume)) => {
+                    update_timer(state, name, |timer| {
+                        let (before, after) = timer.extend(seconds);
+                        eprintln!(
+                            "timer {name:?} extended by {seconds}s: was {before}s, now {after}s"
+                        );
+                        if resume && before == 0 {
+                            timer.resume();
+                        }
+                    })
+                    .await
+                }
+                Ok((Err((status, message)), _)) => error_response(status, &message),
+                Err(err) => err,
+            }
+        }
+        (Method::POST, "/reduce") => {
+            let example = r#"{"seconds": 300, "force": false}"#;
+            match parse_json_body::<ReduceBody>(req, example).await {
+                Ok(body) => {
+                    let mut guard = state.lock_timers().await;
+                    match guard.get_mut(name) {
+                        Some(timer) => {
+                            let reduced_by_secs = timer.reduce(body.seconds, body.force);
+                            let response = ReduceResponse {
+                                snapshot: timer.snapshot(),
+                                reduced_by_secs,
+                                clamped: reduced_by_secs < body.seconds,
+                            };
+                            json_response(StatusCode::OK, &response)
+                        }
+                        None => error_response(
+                            StatusCode::NOT_FOUND,
+                            &format!("timer {name:?} not found"),
+                        ),
+                    }
+                }
+                Err(err) => err,
+            }
+        }
+        _ => match allowed_methods(route) {
+            allowed if allowed.is_empty() => {
+                error_response(StatusCode::NOT_FOUND, "route not found")
+            }
+            allowed => {
+                let allowed = allowed.join(", ");
+                let mut response = error_response(
+                    StatusCode::METHOD_NOT_ALLOWED,
+                    &format!("method not allowed; use {allowed}"),
+                );
+                response.headers_mut().insert(
+                    ALLOW,
+                    HeaderValue::from_str(&allowed).expect("method names are valid header values"),
+                );
+                response
+            }
+        },
+    }
+}
+
+/// Refreshes every timer once per tick so completions and scheduled actions
+/// happen even when nobody is polling. While nothing is running or scheduled
+/// only a request can change that, so it sleeps until one has been handled.
+async fn watchdog(state: SharedState) {
+    let mut ticker = tokio::time::interval(TICK);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        ticker.tick().await;
+        let mut guard = state.lock_timers().await;
+        for (name, timer) in guard.iter_mut() {
+            timer.refresh();
+            report_completion(&state, name, timer);
+        }
+        let idle = guard
+            .values()
+            .all(|timer| !timer.running && timer.schedules.is_empty());
+        drop(guard);
+        if idle {
+            state.handled_request.notified().await;
+            ticker.reset_immediately();
+        }
+    }
+}
+
+/// syslog(3) never reports failure, so a missing `/dev/log` is only warned
+/// about; messages are then silently dropped.
+fn open_syslog() {
+    if !Path::new("/dev/log").exists() {
+        eprintln!("warning: /dev/log not found; completions will not reach syslog");
+    }
+    // SAFETY: `openlog` keeps the identifier pointer, and a C string literal
+    // lives for the whole program.
+    unsafe { libc::openlog(c"timersock".as_ptr(), libc::LOG_PID, libc::LOG_DAEMON) };
+}
+
+/// Runs the completion hooks once per completion, whoever noticed it.
+fn report_completion(state: &AppState, name: &str, timer: &mut TimerState) {
+    if !timer.take_completion() {
+        return;
+    }
+    if let Some(stats) = &state.stats {
+        stats.completions.fetch_add(1, Ordering::Relaxed);
+    }
+    if state.config.syslog {
+        log_completion(name, timer);
+    }
+}
+
+fn log_completion(name: &str, timer: &TimerState) {
+    let message = format!(
+        "timer {name:?} completed: duration_secs={} running_secs={}",
+        timer.original_duration_secs, timer.total_running_secs
+    );
+    let Ok(message) = CString::new(message) else {
+        return;
     };
+    // SAFETY: the format string consumes exactly the one C string passed.
+    unsafe { libc::syslog(libc::LOG_NOTICE, c"%s".as_ptr(), message.as_ptr()) };
+}
 
-    Ok(response)
+/// Socket path for `remove_socket_at_exit`, set once the listener is bound.
+static CLEANUP_SOCKET_PATH: OnceLock<CString> = OnceLock::new();
+
+/// Runs on every `exit`, including an error returned from `main` or a panic
+/// unwinding out of it, but not on `abort` or fatal signals. It only reads the
+/// already-initialised path and calls `unlink(2)`, so it neither allocates nor
+/// takes locks.
+extern "C" fn remove_socket_at_exit() {
+    if let Some(path) = CLEANUP_SOCKET_PATH.get() {
+        // SAFETY: `path` is NUL-terminated and lives for the rest of the program.
+        unsafe { libc::unlink(path.as_ptr()) };
+    }
+}
+
+fn register_socket_cleanup(socket_path: &Path) -> anyhow::Result<()> {
+    let path = CString::new(socket_path.as_os_str().as_bytes())
+        .context("socket path contains a NUL byte")?;
+    if CLEANUP_SOCKET_PATH.set(path).is_ok() {
+        // SAFETY: the handler is a plain `extern "C" fn` with no arguments.
+        if unsafe { libc::atexit(remove_socket_at_exit) } != 0 {
+            eprintln!("warning: failed to register socket cleanup at exit");
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug)]
+struct MirroredRequest {
+    path: String,
+    body: Bytes,
+}
+
+/// Replays queued mutations to the peer one at a time, so it applies them in
+/// the order this server did.
+async fn run_mirror(peer: PathBuf, mut queue: mpsc::Receiver<MirroredRequest>) {
+    while let Some(request) = queue.recv().await {
+        let forwarded = tokio::time::timeout(MIRROR_TIMEOUT, forward_to_mirror(&peer, &request));
+        let result = match forwarded.await {
+            Ok(result) => result,
+            Err(_) => Err(anyhow::anyhow!("timed out after {MIRROR_TIMEOUT:?}")),
+        };
+        if let Err(err) = result {
+            eprintln!(
+                "warning: failed to mirror POST {} to {}: {err:#}",
+                request.path,
+                peer.display()
+            );
+        }
+    }
+}
+
+async fn forward_to_mirror(peer: &Path, request: &MirroredRequest) -> anyhow::Result<()> {
+    let stream = tokio::net::UnixStream::connect(peer)
+        .await
+        .context("failed to connect")?;
+    let (mut sender, connection) = hyper::client::conn::http1::handshake(TokioIo::new(stream))
+        .await
+        .context("HTTP handshake failed")?;
+    tokio::spawn(connection);
+
+    let forwarded = Request::post(&request.path)
+        .header(HOST, "localhost")
+        .header(CONTENT_TYPE, "application/json")
+        .header(MIRRORED_HEADER, "1")
+        .body(Full::new(request.body.clone()))
+        .context("failed to build request")?;
+    let response = sender
+        .send_request(forwarded)
+        .await
+        .context("request failed")?;
+    if !response.status().is_success() {
+        anyhow::bail!("peer answered {}", response.status());
+    }
+    Ok(())
+}
+
+/// Answers the connection's first request with `503` and closes it, for
+/// connections that found no free slot under `--max-connections`.
+async fn reject_busy(io: TokioIo<tokio::net::UnixStream>, retry_after_secs: u64) {
+    let service = service_fn(move |_req: Request<Incoming>| async move {
+        let received = Instant::now();
+        let mut response = error_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "server is at its connection limit; try again later",
+        );
+        response
+            .headers_mut()
+            .insert(RETRY_AFTER, HeaderValue::from(retry_after_secs));
+        set_response_time(&mut response, received);
+        Ok::<_, Infallible>(response)
+    });
+    if let Err(err) = http1::Builder::new()
+        .keep_alive(false)
+        .max_buf_size(MAX_BUF_SIZE)
+        .serve_connection(io, service)
+        .await
+    {
+        eprintln!("connection error: {err}");
+    }
+}
+
+/// Refuses to start while the file names a live process; a file left behind
+/// by a dead one is overwritten with a warning.
+fn write_pid_file(path: &Path) -> anyhow::Result<()> {
+    if let Ok(contents) = std::fs::read_to_string(path) {
+        match contents.trim().parse::<libc::pid_t>() {
+            Ok(pid) if pid > 0 && process_exists(pid) => anyhow::bail!(
+                "pid file {} names running process {pid}; is another timersock running?",
+                path.display()
+            ),
+            _ => eprintln!(
+                "warning: overwriting stale pid file {} ({:?})",
+                path.display(),
+                contents.trim()
+            ),
+        }
+    }
+    std::fs::write(path, format!("{}\n", std::process::id()))
+        .with_context(|| format!("failed to write pid file {}", path.display()))
+}
+
+fn process_exists(pid: libc::pid_t) -> bool {
+    // SAFETY: signal 0 only checks whether the process could be signalled.
+    let result = unsafe { libc::kill(pid, 0) };
+    // EPERM means it exists but belongs to someone else.
+    result == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+fn bind_listener(socket_path: &Path, backlog: i32) -> anyhow::Result<UnixListener> {
+    let socket =
+        Socket::new(Domain::UNIX, Type::STREAM, None).context("failed to create unix socket")?;
+    let address = SockAddr::unix(socket_path)
+        .with_context(|| format!("invalid unix socket path {}", socket_path.display()))?;
+
+    socket
+        .bind(&address)
+        .with_context(|| format!("failed to bind unix socket at {}", socket_path.display()))?;
+    socket
+        .listen(backlog)
+        .with_context(|| format!("failed to listen on unix socket with backlog {backlog}"))?;
+    socket
+        .set_nonblocking(true)
+        .context("failed to make unix socket non-blocking")?;
+
+    UnixListener::from_std(socket.into()).context("failed to register unix socket with tokio")
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let socket_path =
-        std::env::var("TIMER_SOCK").unwrap_or_else(|_| DEFAULT_SOCKET_PATH.to_string());
+    let args = Args::parse();
+    let socket_path = args.socket;
 
-    if Path::new(&socket_path).exists() {
-        std::fs::remove_file(&socket_path)
-            .with_context(|| format!("failed to remove existing socket at {}", socket_path))?;
+    // Before touching the socket, which may belong to a running server.
+    if let Some(pid_file) = &args.pid_file {
+        write_pid_file(pid_file)?;
+    }
+
+    if socket_path.exists() {
+        std::fs::remove_file(&socket_path).with_context(|| {
+            format!(
+                "failed to remove existing socket at {}",
+                socket_path.display()
+            )
+        })?;
     }
 
-    let listener = UnixListener::bind(&socket_path)
-        .with_context(|| format!("failed to bind unix socket at {}", socket_path))?;
+    let listener = bind_listener(&socket_path, args.backlog)?;
+    if !args.keep_socket {
+        register_socket_cleanup(&socket_path)?;
+    }
 
-    let state: SharedState = Arc::new(RwLock::new(TimerState::default()));
+    let config = ServerConfig {
+        socket_path: socket_path.clone(),
+        backlog: args.backlog,
+        keep_socket: args.keep_socket,
+        allow_overtime: args.allow_overtime,
+        min_secs: args.min_secs,
+        syslog: args.syslog,
+        stats: args.stats,
+        pid_file: args.pid_file.clone(),
+        max_connections: args.max_connections,
+        queue_timeout_ms: args.queue_timeout,
+        retry_after_secs: args.retry_after,
+        strict_methods: args.strict_methods,
+        mirror: args.mirror.clone(),
+        max_header_bytes: MAX_BUF_SIZE,
+        tick_secs: TICK.as_secs(),
+    };
+    let timers = Timers::from([(DEFAULT_TIMER.to_string(), TimerState::new(&config))]);
+    let state: SharedState = Arc::new(AppState {
+        timers: RwLock::new(timers),
+        config,
+        stats: args.stats.then(Stats::default),
+        handled_request: Notify::new(),
+        mirror: args.mirror.clone().map(|peer| {
+            let (sender, queue) = mpsc::channel(MIRROR_QUEUE_LEN);
+            tokio::spawn(run_mirror(peer, queue));
+            sender
+        }),
+    });
+    if args.syslog {
+        open_syslog();
+    }
+    tokio::spawn(watchdog(Arc::clone(&state)));
+    let slots = args
+        .max_connections
+        .map(|max| Arc::new(Semaphore::new(max as usize)));
+    let queue_timeout = Duration::from_millis(args.queue_timeout);
+    let retry_after_secs = args.retry_after;
+    let mut terminate =
+        signal(SignalKind::terminate()).context("failed to install SIGTERM handler")?;
 
     loop {
-        let (stream, _) = listener.accept().await?;
+        let (stream, _) = tokio::select! {
+            accepted = listener.accept() => accepted?,
+            _ = tokio::signal::ctrl_c() => break,
+            _ = terminate.recv() => break,
+        };
         let io = TokioIo::new(stream);
         let state = Arc::clone(&state);
+        let slots = slots.clone();
 
         tokio::spawn(async move {
-            let service = service_fn(move |req| handle_request(req, Arc::clone(&state)));
+            // Held for the lifetime of the connection.
+            let _slot = match slots {
+                Some(slots) => {
+                    match tokio::time::timeout(queue_timeout, slots.acquire_owned()).await {
+                        Ok(Ok(permit)) => Some(permit),
+                        _ => {
+                            reject_busy(io, retry_after_secs).await;
+                            return;
+                        }
+                    }
+                }
+                None => None,
+            };
+            if let Some(stats) = &state.stats {
+                stats.connection_opened();
+            }
+            let service_state = Arc::clone(&state);
+            let service = service_fn(move |req| serve_request(req, Arc::clone(&service_state)));
 
-            if let Err(err) = http1::Builder::new().serve_connection(io, service).await {
+            if let Err(err) = http1::Builder::new()
+                .keep_alive(true)
+                .max_buf_size(MAX_BUF_SIZE)
+                .serve_connection(io, service)
+                .await
+            {
                 eprintln!("connection error: {err}");
             }
+            if let Some(stats) = &state.stats {
+                stats.connection_closed();
+            }
         });
     }
+
+    if !args.keep_socket {
+        std::fs::remove_file(&socket_path)
+            .with_context(|| format!("failed to remove socket at {}", socket_path.display()))?;
+    }
+    if let Some(pid_file) = &args.pid_file {
+        std::fs::remove_file(pid_file)
+            .with_context(|| format!("failed to remove pid file {}", pid_file.display()))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percent_decode_handles_escapes() {
+        assert_eq!(percent_decode("tea").as_deref(), Some("tea"));
+        assert_eq!(percent_decode("a%2Cb%2c").as_deref(), Some("a,b,"));
+        assert_eq!(percent_decode("my%20tea").as_deref(), Some("my tea"));
+        assert_eq!(percent_decode("%E2%8F%B1").as_deref(), Some("⏱"));
+        assert_eq!(percent_decode("bad%2"), None);
+        assert_eq!(percent_decode("bad%+1"), None);
+        assert_eq!(percent_decode("%FF"), None);
+    }
+
+    /// A timer started `secs_ago` seconds ago with `seconds` on the clock.
+    fn started_ago(seconds: u64, secs_ago: u64) -> (TimerState, Instant) {
+        let mut timer = TimerState::default();
+        timer.start(seconds);
+        let started = Instant::now() - Duration::from_secs(secs_ago);
+        timer.updated_at = started;
+        (timer, started)
+    }
+
+    #[test]
+    fn refresh_after_a_long_gap_dates_the_completion_exactly() {
+        let (mut timer, started) = started_ago(10, 3600);
+        timer.refresh();
+
+        assert_eq!(timer.time_left_secs, 0);
+        assert!(!timer.running);
+        assert_eq!(timer.completed_at, Some(started + Duration::from_secs(10)));
+        assert!(timer.take_completion());
+    }
+
+    fn running_with_floor(seconds: u64, min_secs: u64) -> TimerState {
+        let mut timer = TimerState {
+            min_secs,
+            ..TimerState::default()
+        };
+        timer.start(seconds);
+        timer
+    }
+
+    #[test]
+    fn reduce_above_the_floor_takes_the_full_amount() {
+        let mut timer = running_with_floor(100, 30);
+        assert_eq!(timer.reduce(20, false), 20);
+        assert_eq!(timer.time_left_secs, 80);
+        assert!(timer.running);
+    }
+
+    #[test]
+    fn reduce_clamps_at_the_floor_unless_forced() {
+        let mut timer = running_with_floor(100, 30);
+        assert_eq!(timer.reduce(90, false), 70);
+        assert_eq!(timer.time_left_secs, 30);
+        assert!(timer.running);
+
+        assert_eq!(timer.reduce(25, true), 25);
+        assert_eq!(timer.time_left_secs, 5);
+    }
+
+    #[test]
+    fn mutations_bump_modified_at() {
+        let long_ago = SystemTime::now() - Duration::from_secs(3600);
+        let mutations: [fn(&mut TimerState); 4] = [
+            |timer| timer.pause(),
+            |timer| timer.resume(),
+            |timer| {
+                timer.extend(30);
+            },
+            |timer| timer.start(60),
+        ];
+        for mutate in mutations {
+            let mut timer = TimerState::default();
+            timer.start(120);
+            timer.modified_at = long_ago;
+            mutate(&mut timer);
+            assert!(timer.modified_at > long_ago);
+            assert!(timer.snapshot().modified_at_unix > system_time_to_unix(long_ago));
+        }
+    }
+
+    #[test]
+    fn pause_at_pauses_once_at_the_threshold() {
+        let (mut timer, _) = started_ago(100, 90);
+        timer.pause_at = Some(30);
+        timer.refresh();
+        assert!(!timer.running);
+        assert_eq!(timer.time_left_secs, 30);
+        assert_eq!(timer.pause_at, None);
+
+        timer.resume();
+        timer.updated_at = Instant::now() - Duration::from_secs(10);
+        timer.refresh();
+        assert!(timer.running);
+        assert_eq!(timer.time_left_secs, 20);
+    }
+
+    #[tokio::test]
+    async fn bind_listener_accepts_a_custom_backlog() {
+        let dir = std::env::temp_dir().join(format!("timersock-backlog-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let socket_path = dir.join("timer.sock");
+
+        let listener = bind_listener(&socket_path, 16).expect("bind with backlog 16");
+        let client = tokio::net::UnixStream::connect(&socket_path).await.unwrap();
+        let (_server_side, _) = listener.accept().await.unwrap();
+        drop(client);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -1,19 +1,108 @@
-use std::io::{Read, Write};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fmt;
+use std::io::{ErrorKind, Read, Write};
 use std::os::unix::net::UnixStream;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use anyhow::{Context, Result, bail};
-use clap::{Parser, Subcommand};
+use anyhow::{Context, Result, anyhow, bail};
+use clap::{Parser, Subcommand, ValueEnum};
 use serde::{Deserialize, Serialize};
+use socket2::{Domain, SockAddr, Socket, Type};
 
-const DEFAULT_SOCKET_PATH: &str = "/tmp/timer.sock";
+/// A macro rather than only a const so `--version` can embed it with `concat!`.
+macro_rules! default_socket_path {
+    () => {
+        "/tmp/timer.sock"
+    };
+}
+
+/// `print!` and `println!` that return an error instead of panicking; see
+/// `stdout_result`.
+macro_rules! out {
+    ($($arg:tt)*) => {{
+        let mut stdout = std::io::stdout().lock();
+        stdout_result(write!(stdout, $($arg)*).and_then(|()| stdout.flush()))
+    }};
+}
+
+macro_rules! outln {
+    ($($arg:tt)*) => {{
+        let mut stdout = std::io::stdout().lock();
+        stdout_result(writeln!(stdout, $($arg)*).and_then(|()| stdout.flush()))
+    }};
+}
+
+const DEFAULT_SOCKET_PATH: &str = default_socket_path!();
+const DEFAULT_WARN_THRESHOLD_SECS: u64 = 300;
+const DEFAULT_MIN_DURATION_WARN_SECS: u64 = 10;
+const MAX_EXIT_SECS: u64 = 254;
+const MAX_TWO_DIGIT_HOURS_SECS: u64 = 99 * 3600 + 59 * 60 + 59;
+// Exit codes, all kept here so they cannot collide by accident. Other
+// failures exit 1.
+const EXIT_NOT_READY: i32 = 1;
+const EXIT_NOT_FOUND: i32 = 2;
+const EXIT_STATE_CONFLICT: i32 = 3;
+/// Deliberately the same as `state_conflict`: the timer is in the wrong state.
+/// `status` only reads, so the server never answers it with a conflict.
+const EXIT_EXPECTATION_FAILED: i32 = EXIT_STATE_CONFLICT;
+const EXIT_VALIDATION_ERROR: i32 = 4;
+const EXIT_INTERNAL: i32 = 5;
+const EXIT_STALE_SOCKET: i32 = 6;
+const EXIT_SECS_ERROR: i32 = 255;
+const WAIT_POLL: Duration = Duration::from_secs(30);
+/// Left between a `/wait` poll's answer and `--timeout` for the response itself.
+const WAIT_POLL_MARGIN: Duration = Duration::from_millis(500);
+/// The newest `TimerSnapshot` schema this CLI understands.
+const SNAPSHOT_SCHEMA_VERSION: u32 = 1;
+const MAX_BUSY_RETRIES: u32 = 3;
 
 #[derive(Debug, Parser)]
-#[command(name = "timer", about = "CLI wrapper for timersock")]
+#[command(
+    name = "timer",
+    about = "CLI wrapper for timersock",
+    version = concat!(
+        env!("CARGO_PKG_VERSION"),
+        " (default socket: ",
+        default_socket_path!(),
+        ")"
+    )
+)]
 struct Cli {
     #[arg(long, env = "TIMER_SOCK", default_value = DEFAULT_SOCKET_PATH)]
     socket: PathBuf,
 
+    /// Give up if a whole request (connect, send, receive) takes longer than
+    /// this many seconds.
+    #[arg(long, global = true, value_parser = parse_timeout)]
+    timeout: Option<Duration>,
+
+    /// Fail immediately if the server is not accepting connections right now.
+    #[arg(long, global = true)]
+    no_wait: bool,
+
+    /// Print how long the server took to handle each request to stderr.
+    #[arg(long, global = true)]
+    show_timing: bool,
+
+    /// IANA timezone, e.g. `Europe/Berlin`, for wall-clock times instead of
+    /// the system's.
+    #[arg(long, global = true, value_parser = parse_timezone)]
+    tz: Option<String>,
+
+    /// Unit for bare numbers given to `start`, `start-aligned`, `extend` and
+    /// `reduce`. Durations with a unit, like `30s`, are unaffected.
+    #[arg(
+        long,
+        global = true,
+        env = "TIMER_UNIT",
+        value_enum,
+        default_value_t = DurationUnit::Seconds
+    )]
+    unit: DurationUnit,
+
     #[command(subcommand)]
     command: Command,
 }
@@ -23,149 +112,3040 @@ enum Command {
     Status {
         #[arg(long)]
         json: bool,
+
+        /// Print a compact ` ⏱4:32` status without a newline for shell prompts.
+        #[arg(long, conflicts_with_all = ["json", "names"])]
+        ps1: bool,
+
+        /// Query several servers in parallel and print a one-line summary.
+        #[arg(
+            long,
+            value_delimiter = ',',
+            conflicts_with_all = ["json", "ps1", "names", "expect"]
+        )]
+        all_sockets: Vec<PathBuf>,
+
+        /// Alternative output format for the default timer: `json-compact-secs`,
+        /// or `@<path>` to render a template file.
+        #[arg(
+            long,
+            value_parser = parse_status_format,
+            conflicts_with_all = ["json", "ps1", "names"]
+        )]
+        format: Option<StatusFormat>,
+
+        /// Atomically write the JSON snapshot, or the `--format` output, to
+        /// this file instead of stdout; with `--json-stream`, append each line
+        /// to it. `-` means stdout.
+        #[arg(long, conflicts_with_all = ["ps1", "names"])]
+        output_file: Option<PathBuf>,
+
+        /// Exit with `min(time_left_secs, 254)` instead of printing; 255 means
+        /// the status could not be read.
+        #[arg(
+            long,
+            conflicts_with_all = ["json", "ps1", "all_sockets", "format", "output_file", "names", "expect"]
+        )]
+        exit_secs: bool,
+
+        /// Print the server's JSON body as-is, without decoding it first.
+        #[arg(
+            long,
+            conflicts_with_all = ["json", "ps1", "all_sockets", "format", "output_file", "exit_secs", "expect"]
+        )]
+        raw_json: bool,
+
+        /// Append the local time the running timer will expire at.
+        #[arg(long)]
+        show_expiry: bool,
+
+        /// Print one JSON snapshot per line every `--every` seconds until the
+        /// timer stops at zero.
+        #[arg(
+            long,
+            conflicts_with_all = ["json", "ps1", "all_sockets", "format", "exit_secs", "raw_json", "names", "expect"]
+        )]
+        json_stream: bool,
+
+        /// Poll interval in seconds for `--json-stream`.
+        #[arg(
+            long,
+            default_value_t = 1,
+            value_parser = clap::value_parser!(u64).range(1..),
+            requires = "json_stream"
+        )]
+        every: u64,
+
+        /// Flag timers with less than this many seconds left with
+        /// `low_time_warning` in JSON and `[LOW TIME]` in plain output.
+        #[arg(
+            long,
+            env = "LOW_TIME_THRESHOLD_SECS",
+            default_value_t = DEFAULT_WARN_THRESHOLD_SECS
+        )]
+        warn_threshold: u64,
+
+        /// Exit with code 3 unless the timer is running. A `state_conflict`
+        /// error also exits 3, but `status` never receives one.
+        #[arg(long, group = "expect")]
+        expect_running: bool,
+
+        /// Exit with code 3 unless the timer is paused with time left.
+        #[arg(long, group = "expect")]
+        expect_paused: bool,
+
+        /// Exit with code 3 unless the timer is stopped at zero.
+        #[arg(long, group = "expect")]
+        expect_expired: bool,
+
+        /// Print nothing; useful with the `--expect-*` assertions.
+        #[arg(
+            long,
+            short,
+            conflicts_with_all = ["output_file", "all_sockets", "raw_json", "json_stream", "names"]
+        )]
+        quiet: bool,
+
+        /// When the server is down (no socket, or nothing listening), print an
+        /// idle snapshot and exit 0 instead of failing.
+        #[arg(
+            long,
+            conflicts_with_all = ["all_sockets", "raw_json", "exit_secs", "json_stream", "names"]
+        )]
+        offline_as_idle: bool,
+
+        /// When the server is down, show the last snapshot a `status` call
+        /// saw, marked `[cached Ns ago]`, instead of failing.
+        #[arg(
+            long,
+            conflicts_with_all = ["all_sockets", "raw_json", "exit_secs", "json_stream", "names", "offline_as_idle"]
+        )]
+        cached: bool,
+
+        /// Skip checking that `time_left_hms` agrees with `time_left_secs`.
+        #[arg(long)]
+        no_validate: bool,
+
+        /// Do not warn when the hours in `time_left_hms` need more than two digits.
+        #[arg(long)]
+        no_warn: bool,
+
+        /// Print the socket path being used to stderr before connecting.
+        #[arg(long, short)]
+        verbose: bool,
+
+        /// Print only the seconds left as a plain integer, `0` when stopped.
+        #[arg(
+            long,
+            conflicts_with_all = ["json", "ps1", "all_sockets", "format", "output_file", "exit_secs", "raw_json", "show_expiry", "json_stream", "names"]
+        )]
+        count_down: bool,
+
+        /// Print `time_left_hms time_left_secs running` as fixed-width columns.
+        #[arg(
+            long,
+            conflicts_with_all = ["json", "ps1", "format", "exit_secs", "raw_json", "json_stream", "count_down", "group_digits", "show_expiry", "names", "all_sockets"]
+        )]
+        machine: bool,
+
+        /// Write seconds with thousands separators, e.g. `604,800`.
+        #[arg(
+            long,
+            conflicts_with_all = ["json", "ps1", "format", "exit_secs", "raw_json", "json_stream", "count_down"]
+        )]
+        group_digits: bool,
+
+        /// Named timers to poll in one request instead of the default timer.
+        #[arg(conflicts_with = "expect", value_parser = parse_label)]
+        names: Vec<String>,
     },
     Start {
-        seconds: u64,
+        /// A number in `--unit`, or a duration like `90s`, `15m` or `1h30m`.
+        /// Defaults to `default_duration` from the nearest `.timerrc`.
+        #[arg(value_parser = parse_duration_arg)]
+        seconds: Option<DurationArg>,
+
+        /// Also print the local time the timer will expire at.
+        #[arg(long, short)]
+        verbose: bool,
+
+        /// Fail instead of restarting a timer that is already running. Set
+        /// `TIMER_NO_CLOBBER=1` to make this the default.
+        #[arg(
+            long,
+            env = "TIMER_NO_CLOBBER",
+            value_parser = clap::builder::FalseyValueParser::new()
+        )]
+        no_clobber: bool,
+
+        /// Restart a running timer even with `--no-clobber` or `TIMER_NO_CLOBBER`.
+        #[arg(long)]
+        force: bool,
+
+        /// Create or restart the named timer instead of the default one.
+        #[arg(long, value_parser = parse_label)]
+        label: Option<String>,
+
+        /// Warn on stderr when starting for fewer than this many seconds, in
+        /// case minutes were meant.
+        #[arg(long, default_value_t = DEFAULT_MIN_DURATION_WARN_SECS)]
+        min_duration_warn: u64,
+
+        /// Do not warn about suspiciously short durations.
+        #[arg(long, visible_alias = "yes", short = 'y')]
+        no_warn: bool,
+
+        /// Run this many cycles back to back instead of once.
+        #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+        repeat: Option<u32>,
+
+        /// Seconds added to each cycle after the first; negative to shorten
+        /// them. Cycles never drop below one second.
+        #[arg(
+            long,
+            requires = "repeat",
+            allow_negative_numbers = true,
+            default_value_t = 0
+        )]
+        step: i64,
+
+        /// Pause once when this much time is left, e.g. `10` or `2m`, so you
+        /// can decide whether to carry on.
+        #[arg(long, value_parser = parse_duration_arg, conflicts_with = "repeat")]
+        pause_at: Option<DurationArg>,
+
+        /// Print the duration and expiry time without contacting the server.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Wait for the next whole minute on the local clock, then start.
+    StartAligned {
+        #[arg(value_parser = parse_duration_arg)]
+        seconds: DurationArg,
+
+        /// Start the named timer instead of the default one.
+        #[arg(long, value_parser = parse_label)]
+        label: Option<String>,
     },
     Pause,
     Resume,
     Toggle,
+    /// A negative duration (`extend -60`) reduces the timer instead.
+    #[command(allow_negative_numbers = true)]
     Extend {
+        #[arg(required_unless_present = "percent", value_parser = parse_signed_duration_arg)]
+        seconds: Option<SignedDurationArg>,
+
+        /// Extend by this percentage of the duration the timer was started with.
+        #[arg(long, conflicts_with = "seconds")]
+        percent: Option<u64>,
+
+        /// Print the resulting time left without changing the timer.
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Restart the timer if it has already run out, instead of leaving
+        /// the added time paused.
+        #[arg(long)]
+        resume_on_extend: bool,
+    },
+    Reduce {
+        #[arg(required_unless_present = "percent", value_parser = parse_duration_arg)]
+        seconds: Option<DurationArg>,
+
+        /// Reduce by this percentage of the duration the timer was started with.
+        #[arg(long, conflicts_with = "seconds")]
+        percent: Option<u64>,
+
+        /// Go below the server's `--min-secs` floor.
+        #[arg(long)]
+        force: bool,
+
+        /// Print the resulting time left without changing the timer.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Take two snapshots `--interval` seconds apart and report the change.
+    Diff {
+        #[arg(long, default_value_t = 10)]
+        interval: u64,
+
+        #[arg(long)]
+        json: bool,
+    },
+    /// Run an interval program such as `30s work, 15s rest x8`.
+    Interval {
+        pattern: String,
+    },
+    /// Measure round-trip latency to the server without touching any timer.
+    Ping {
+        #[arg(long, short, default_value_t = 5, value_parser = clap::value_parser!(u32).range(1..))]
+        count: u32,
+
+        #[arg(long)]
+        json: bool,
+    },
+    /// Exit 0 if the server answers `HEAD /healthz`, 1 otherwise; prints nothing.
+    Ready,
+    /// Count running, paused and finished timers and show the next to finish.
+    Summary {
+        #[arg(long)]
+        json: bool,
+    },
+    /// List every timer with its snapshot.
+    List {
+        /// Print one JSON array of timers.
+        #[arg(long, conflicts_with = "ndjson")]
+        json: bool,
+
+        /// Print one JSON object per line instead of an array.
+        #[arg(long, conflicts_with = "machine")]
+        ndjson: bool,
+
+        /// Print fixed-width columns: name, time left, seconds and running.
+        #[arg(long, conflicts_with = "json")]
+        machine: bool,
+    },
+    /// Cancel pending scheduled actions before they fire.
+    Cancel {
+        /// Only cancel actions of this kind.
+        #[arg(value_enum)]
+        action: Option<ScheduledAction>,
+    },
+    /// Pause every running timer at once.
+    PauseAll {
+        /// Only list the timers that would be paused.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Resume every paused timer at once.
+    ResumeAll {
+        /// Only list the timers that would be resumed.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Schedule a pause, resume or stop; without arguments, list pending ones.
+    Schedule {
+        #[arg(value_enum, requires = "at")]
+        action: Option<ScheduledAction>,
+
+        /// Local time as `HH:MM[:SS]` (today), or a relative offset like `+15m`.
+        #[arg(long, requires = "action")]
+        at: Option<String>,
+    },
+    /// Block until the timer has fewer than `--below` seconds left.
+    Wait {
+        #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+        below: u64,
+    },
+    /// Show the server's effective settings.
+    Config {
+        #[command(subcommand)]
+        action: Option<ConfigAction>,
+    },
+    /// Show or change the message announced when the timer completes.
+    Message {
+        #[command(subcommand)]
+        action: Option<MessageAction>,
+    },
+    /// Print how much of the timer has elapsed as a bar like `[#####-----]`.
+    Bar {
+        /// Number of cells between the brackets.
+        #[arg(long, default_value_t = 20, value_parser = clap::value_parser!(u16).range(1..))]
+        width: u16,
+
+        /// Character for elapsed cells.
+        #[arg(long, default_value_t = '#')]
+        fill: char,
+
+        /// Character for remaining cells.
+        #[arg(long, default_value_t = '-')]
+        empty: char,
+    },
+    /// Start the `egg` timer for a boiled egg.
+    Egg {
+        #[arg(value_enum)]
+        doneness: EggDoneness,
+    },
+    /// Convert a duration like `1h30m` to seconds, without contacting the server.
+    Parse {
+        duration: String,
+    },
+    /// Print seconds as `HH:MM:SS` and as a duration like `1h30m`, without
+    /// contacting the server.
+    Fmt {
         seconds: u64,
     },
+    /// Print a string that `timer load` can use to recreate this timer.
+    Share {
+        /// Share the named timer instead of the default one.
+        #[arg(long, value_parser = parse_label)]
+        label: Option<String>,
+    },
+    /// Start a timer from a string printed by `timer share`.
+    Load {
+        code: String,
+
+        /// Fail instead of restarting a timer that is already running.
+        #[arg(long)]
+        no_clobber: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum ConfigAction {
+    /// Print the settings as JSON (the default).
+    Show,
+}
+
+#[derive(Debug, Subcommand)]
+enum MessageAction {
+    /// Set the completion message, e.g. `message set "Tea is ready"`.
+    Set { text: String },
+    /// Go back to the default message.
+    Clear,
+}
+
+#[derive(Debug, Clone)]
+enum StatusFormat {
+    /// `{"s":<time_left_secs>,"r":<running>}` with nothing else.
+    JsonCompactSecs,
+    /// A template file, read on every invocation so edits apply right away.
+    Template(PathBuf),
+}
+
+impl StatusFormat {
+    /// The exact output for `snapshot`, trailing newline included, whether it
+    /// goes to stdout or `--output-file`.
+    fn render(&self, snapshot: &TimerSnapshot) -> Result<String> {
+        match self {
+            Self::JsonCompactSecs => {
+                let compact = CompactSnapshot {
+                    s: snapshot.time_left_secs,
+                    r: snapshot.running,
+                };
+                let line =
+                    serde_json::to_string(&compact).context("failed to encode JSON output")?;
+                Ok(format!("{line}\n"))
+            }
+            Self::Template(path) => {
+                let template = std::fs::read_to_string(path)
+                    .with_context(|| format!("failed to read template {}", path.display()))?;
+                render_template(&template, snapshot)
+                    .with_context(|| format!("invalid template {}", path.display()))
+            }
+        }
+    }
+}
+
+/// Placeholders a `--format @<path>` template may use, as `{name}`.
+const TEMPLATE_PLACEHOLDERS: &[&str] = &[
+    "time_left_hms",
+    "time_left_secs",
+    "running",
+    "alt",
+    "original_duration_secs",
+    "overtime_secs",
+    "total_running_secs",
+    "message",
+    "interval_label",
+];
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum EggDoneness {
+    Soft,
+    Medium,
+    Hard,
+}
+
+impl EggDoneness {
+    /// Built-in cooking time, overridable with `TIMER_EGG_<DONENESS>` (e.g.
+    /// `TIMER_EGG_SOFT=5m30s`).
+    fn seconds(self) -> Result<u64> {
+        let (variable, default_secs) = match self {
+            Self::Soft => ("TIMER_EGG_SOFT", 6 * 60),
+            Self::Medium => ("TIMER_EGG_MEDIUM", 8 * 60),
+            Self::Hard => ("TIMER_EGG_HARD", 10 * 60),
+        };
+        match std::env::var(variable) {
+            Ok(value) => parse_duration(&value).with_context(|| format!("invalid {variable}")),
+            Err(_) => Ok(default_secs),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum DurationUnit {
+    Seconds,
+    Minutes,
+    Hours,
+}
+
+/// A duration argument; only a bare number depends on `--unit`.
+#[derive(Debug, Clone, Copy)]
+enum DurationArg {
+    Bare(u64),
+    Explicit(u64),
+}
+
+/// A duration for `extend`, where a leading `-` takes the time off instead.
+#[derive(Debug, Clone, Copy)]
+struct SignedDurationArg {
+    negative: bool,
+    duration: DurationArg,
+}
+
+impl DurationArg {
+    fn to_seconds(self, unit: DurationUnit) -> Result<u64> {
+        match self {
+            Self::Explicit(seconds) => Ok(seconds),
+            Self::Bare(value) => {
+                let multiplier = match unit {
+                    DurationUnit::Seconds => 1,
+                    DurationUnit::Minutes => 60,
+                    DurationUnit::Hours => 3600,
+                };
+                value
+                    .checked_mul(multiplier)
+                    .context("duration is too large")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ScheduledAction {
+    Pause,
+    Resume,
+    Stop,
+}
+
+impl fmt::Display for ScheduledAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ScheduledAction::Pause => "pause",
+            ScheduledAction::Resume => "resume",
+            ScheduledAction::Stop => "stop",
+        })
+    }
+}
+
+/// The broad state `status --expect-*` asserts on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TimerCondition {
+    Running,
+    Paused,
+    Expired,
+}
+
+impl TimerCondition {
+    fn of(snapshot: &TimerSnapshot) -> Self {
+        if snapshot.running {
+            Self::Running
+        } else if snapshot.time_left_secs > 0 {
+            Self::Paused
+        } else {
+            Self::Expired
+        }
+    }
+}
+
+impl fmt::Display for TimerCondition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Running => "running",
+            Self::Paused => "paused",
+            Self::Expired => "expired",
+        })
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+struct Scheduled {
+    action: ScheduledAction,
+    at_unix: u64,
+}
+
+/// Fields an older server does not send take their `idle` values.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
 struct TimerSnapshot {
+    /// Servers from before the field existed speak version 1.
+    #[serde(default = "default_schema_version")]
+    schema_version: u32,
     time_left_secs: u64,
     time_left_hms: String,
     alt: String,
     running: bool,
+    completed: bool,
+    completed_at_unix: Option<u64>,
+    deadline_unix: Option<u64>,
+    original_duration_secs: u64,
+    overtime_secs: u64,
+    total_running_secs: u64,
+    message: String,
+    interval: Option<IntervalProgress>,
+    created_at_unix: u64,
+    modified_at_unix: u64,
+    session_age_secs: u64,
+    pause_at: Option<u64>,
+    /// Computed by the CLI from `--warn-threshold`; the server never sends it.
+    low_time_warning: bool,
 }
 
-#[derive(Debug, Serialize)]
-struct SecondsBody {
-    seconds: u64,
+impl Default for TimerSnapshot {
+    fn default() -> Self {
+        Self::idle()
+    }
 }
 
-fn main() -> Result<()> {
-    let cli = Cli::parse();
+impl TimerSnapshot {
+    /// Stands in for a timer that was never started.
+    fn idle() -> Self {
+        Self {
+            schema_version: SNAPSHOT_SCHEMA_VERSION,
+            time_left_secs: 0,
+            time_left_hms: "00:00:00".to_string(),
+            alt: "default".to_string(),
+            running: false,
+            completed: false,
+            completed_at_unix: None,
+            deadline_unix: None,
+            original_duration_secs: 0,
+            overtime_secs: 0,
+            total_running_secs: 0,
+            message: String::new(),
+            interval: None,
+            created_at_unix: 0,
+            modified_at_unix: 0,
+            session_age_secs: 0,
+            pause_at: None,
+            low_time_warning: false,
+        }
+    }
 
-    match cli.command {
-        Command::Status { json } => {
-            let snapshot: TimerSnapshot = send(&cli.socket, "GET", "/time_left", None)?;
-            if json {
-                if !(snapshot.running == false && snapshot.time_left_secs == 0) {
-                    println!(
-                        "{}",
-                        serde_json::to_string(&snapshot).context("failed to encode JSON output")?
-                    );
-                }
+    /// A timer at zero has run out rather than running low.
+    fn is_low_time(&self, threshold_secs: u64) -> bool {
+        self.time_left_secs > 0 && self.time_left_secs < threshold_secs
+    }
+
+    fn warn_if_newer_schema(&self) {
+        if self.schema_version > SNAPSHOT_SCHEMA_VERSION {
+            eprintln!(
+                "Warning: server is newer than client (snapshot schema {} > {SNAPSHOT_SCHEMA_VERSION})",
+                self.schema_version
+            );
+        }
+    }
+
+    /// Warns on stderr when the server's two renderings of the time left
+    /// disagree, which would point at a server bug.
+    fn warn_if_inconsistent(&self) {
+        if self.time_left_hms != format_hms(self.time_left_secs) {
+            eprintln!(
+                "Warning: server time_left_hms ({}) does not match time_left_secs ({})",
+                self.time_left_hms, self.time_left_secs
+            );
+        }
+    }
+
+    /// `99:59:59` is the most a fixed-width `HH:MM:SS` field can hold.
+    fn warn_if_wide_hours(&self) {
+        if self.time_left_secs > MAX_TWO_DIGIT_HOURS_SECS {
+            eprintln!(
+                "Warning: remaining time ({}) has > 2 digit hours, which may overflow fixed-width displays.",
+                self.time_left_hms
+            );
+        }
+    }
+
+    fn with_low_time_warning(mut self, threshold_secs: u64) -> Self {
+        self.low_time_warning = self.is_low_time(threshold_secs);
+        self
+    }
+}
+
+/// The alternate form (`{:#}`) groups the digits of second counts.
+impl fmt::Display for TimerSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let alternate = f.alternate();
+        let secs = |secs: u64| {
+            if alternate {
+                group_digits(secs)
             } else {
-                print_snapshot(&snapshot);
+                secs.to_string()
             }
+        };
+        write!(
+            f,
+            "time_left={} time_left_secs={} running={} alt={}",
+            self.time_left_hms,
+            secs(self.time_left_secs),
+            self.running,
+            self.alt
+        )?;
+        if self.overtime_secs > 0 {
+            write!(f, " overtime_secs={}", secs(self.overtime_secs))?;
         }
-        Command::Start { seconds } => {
-            let snapshot: TimerSnapshot = send(
-                &cli.socket,
-                "POST",
-                "/start",
-                Some(serde_json::to_string(&SecondsBody { seconds })?),
+        if let Some(interval) = &self.interval {
+            write!(
+                f,
+                " interval={} rep={}/{}",
+                interval.label, interval.rep, interval.total_reps
             )?;
-            print_snapshot(&snapshot);
-        }
-        Command::Pause => {
-            let snapshot: TimerSnapshot = send(&cli.socket, "POST", "/pause", None)?;
-            print_snapshot(&snapshot);
         }
-        Command::Resume => {
-            let snapshot: TimerSnapshot = send(&cli.socket, "POST", "/resume", None)?;
-            print_snapshot(&snapshot);
-        }
-        Command::Toggle => {
-            let snapshot: TimerSnapshot = send(&cli.socket, "POST", "/toggle", None)?;
-            print_snapshot(&snapshot);
+        if self.low_time_warning {
+            write!(f, " [LOW TIME]")?;
         }
-        Command::Extend { seconds } => {
-            let snapshot: TimerSnapshot = send(
-                &cli.socket,
-                "POST",
-                "/extend",
-                Some(serde_json::to_string(&SecondsBody { seconds })?),
-            )?;
-            print_snapshot(&snapshot);
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct IntervalProgress {
+    label: String,
+    rep: u32,
+    total_reps: u32,
+}
+
+/// Minimal status-bar payload; keys are kept to one letter on purpose.
+#[derive(Debug, Serialize)]
+struct CompactSnapshot {
+    s: u64,
+    r: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct PingReport {
+    count: usize,
+    samples_ms: Vec<f64>,
+    min_ms: f64,
+    avg_ms: f64,
+    max_ms: f64,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct TimersSummary {
+    total: usize,
+    running: usize,
+    paused: usize,
+    finished: usize,
+    soonest: Option<SoonestTimer>,
+}
+
+/// The last snapshot `status` received, kept for `status --cached`.
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedSnapshot {
+    queried_at: u64,
+    snapshot: TimerSnapshot,
+}
+
+impl CachedSnapshot {
+    /// `$XDG_CACHE_HOME/timer/last-snapshot.json`, falling back to `~/.cache`.
+    fn path() -> Result<PathBuf> {
+        let cache_home = match std::env::var_os("XDG_CACHE_HOME").filter(|dir| !dir.is_empty()) {
+            Some(dir) => PathBuf::from(dir),
+            None => std::env::var_os("HOME")
+                .map(|home| PathBuf::from(home).join(".cache"))
+                .context("neither XDG_CACHE_HOME nor HOME is set")?,
+        };
+        Ok(cache_home.join("timer").join("last-snapshot.json"))
+    }
+
+    fn store(snapshot: &TimerSnapshot) -> Result<()> {
+        let path = Self::path()?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("failed to create {}", dir.display()))?;
         }
+        let entry = serde_json::json!({
+            "queried_at": unix_now()?,
+            "snapshot": snapshot,
+        });
+        write_atomic(&path, &serde_json::to_vec(&entry)?)
     }
 
-    Ok(())
+    fn load() -> Result<Self> {
+        let path = Self::path()?;
+        let contents =
+            std::fs::read(&path).with_context(|| format!("failed to read {}", path.display()))?;
+        serde_json::from_slice(&contents)
+            .with_context(|| format!("failed to parse {}", path.display()))
+    }
+
+    fn age_secs(&self) -> u64 {
+        unix_now().map_or(0, |now| now.saturating_sub(self.queried_at))
+    }
+}
+
+/// A `timer list` entry: the snapshot with its timer's name alongside.
+#[derive(Debug, Serialize)]
+struct ListedTimer<'a> {
+    name: &'a str,
+    #[serde(flatten)]
+    snapshot: &'a TimerSnapshot,
 }
 
-fn send<T: for<'de> Deserialize<'de>>(
-    socket_path: &PathBuf,
-    method: &str,
-    path: &str,
-    payload: Option<String>,
-) -> Result<T> {
-    let mut stream = UnixStream::connect(socket_path)
-        .with_context(|| format!("failed to connect to socket {}", socket_path.display()))?;
+#[derive(Debug, Serialize)]
+struct SoonestTimer {
+    name: String,
+    time_left_secs: u64,
+    time_left_hms: String,
+}
 
-    let body = payload.unwrap_or_default();
-    let content_header = if body.is_empty() {
-        String::new()
-    } else {
-        format!(
-            "Content-Type: application/json\r\nContent-Length: {}\r\n",
-            body.len()
-        )
-    };
+#[derive(Debug, Serialize)]
+struct SnapshotDiff {
+    before: TimerSnapshot,
+    after: TimerSnapshot,
+    delta_secs: i64,
+    running_changed: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct ExtendBody {
+    seconds: u64,
+    resume: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct CancelScheduleBody {
+    action: Option<ScheduledAction>,
+}
 
-    let request = format!(
-        "{method} {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n{content_header}\r\n{body}"
-    );
+#[derive(Debug, Deserialize)]
+struct CancelScheduleResponse {
+    cancelled: Vec<Scheduled>,
+}
 
-    stream
-        .write_all(request.as_bytes())
-        .context("failed writing request")?;
-    stream.flush().context("failed flushing request")?;
+#[derive(Debug, Deserialize)]
+struct WaitResponse {
+    #[serde(flatten)]
+    snapshot: TimerSnapshot,
+    reached: bool,
+}
 
-    let mut response = String::new();
-    stream
-        .read_to_string(&mut response)
-        .context("failed reading response")?;
+#[derive(Debug, Serialize)]
+struct StartBody {
+    seconds: u64,
+    no_clobber: bool,
+    pause_at: Option<u64>,
+}
 
-    let (status_line, rest) = response
-        .split_once("\r\n")
-        .context("malformed HTTP response: missing status line")?;
-    let status_code = parse_status_code(status_line)?;
+#[derive(Debug, Serialize, Deserialize)]
+struct MessageBody {
+    message: String,
+}
 
-    let (_, body) = rest
-        .split_once("\r\n\r\n")
-        .context("malformed HTTP response: missing body separator")?;
+/// The setup carried by a `timer share` string.
+#[derive(Debug, Serialize, Deserialize)]
+struct SharedTimer {
+    duration_secs: u64,
+    #[serde(default)]
+    label: Option<String>,
+    #[serde(default)]
+    message: String,
+}
 
-    if !(200..300).contains(&status_code) {
-        bail!("server returned {}: {}", status_code, body);
+impl SharedTimer {
+    fn encode(&self) -> Result<String> {
+        Ok(encode_base64url(&serde_json::to_vec(self)?))
     }
 
-    let parsed = serde_json::from_str::<T>(body).context("failed to parse JSON response")?;
-    Ok(parsed)
+    /// Rejects anything `timer start` itself would not accept, so a mangled
+    /// or hand-edited string fails before it touches the server.
+    fn decode(code: &str) -> Result<Self> {
+        let json = decode_base64url(code.trim()).context("share string is not valid base64")?;
+        let shared: Self =
+            serde_json::from_slice(&json).context("share string does not describe a timer")?;
+        if shared.duration_secs == 0 {
+            bail!("shared timer has a zero duration");
+        }
+        if let Some(label) = &shared.label {
+            parse_label(label).map_err(|err| anyhow!("shared timer label is invalid: {err}"))?;
+        }
+        Ok(shared)
+    }
 }
 
-fn parse_status_code(status_line: &str) -> Result<u16> {
-    let mut parts = status_line.split_whitespace();
-    let _http_version = parts
-        .next()
-        .context("malformed status line: missing HTTP version")?;
-    let code = parts
-        .next()
-        .context("malformed status line: missing status code")?
-        .parse::<u16>()
-        .context("malformed status line: invalid status code")?;
-    Ok(code)
+#[derive(Debug, Serialize)]
+struct ReduceBody {
+    seconds: u64,
+    force: bool,
 }
 
-fn print_snapshot(snapshot: &TimerSnapshot) {
-    println!(
-        "time_left={} time_left_secs={} running={} alt={}",
-        snapshot.time_left_hms, snapshot.time_left_secs, snapshot.running, snapshot.alt
-    );
+#[derive(Debug, Deserialize)]
+struct ReduceResponse {
+    #[serde(flatten)]
+    snapshot: TimerSnapshot,
+    reduced_by_secs: u64,
+    clamped: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct IntervalStep {
+    label: String,
+    seconds: u64,
+    rep: u32,
+}
+
+#[derive(Debug, Serialize)]
+struct IntervalBody {
+    steps: Vec<IntervalStep>,
+    total_reps: u32,
+    no_clobber: bool,
+}
+
+fn main() -> Result<()> {
+    let result = run();
+    if let Err(err) = &result
+        && let Some(code) = err
+            .downcast_ref::<ServerError>()
+            .and_then(ServerError::exit_code)
+    {
+        eprintln!("Error: {err:?}");
+        std::process::exit(code);
+    }
+    result
+}
+
+fn run() -> Result<()> {
+    let cli = Cli::parse();
+    if let Some(tz) = &cli.tz {
+        use_timezone(tz);
+    }
+    if let Command::Status { verbose: true, .. } = cli.command {
+        eprintln!("Using socket: {}", cli.socket.display());
+    }
+    let client = TimerClient::new(cli.socket, cli.timeout, cli.no_wait, cli.show_timing);
+    let unit = cli.unit;
+
+    match cli.command {
+        Command::Status {
+            exit_secs: true, ..
+        } => {
+            let code = match client.send::<TimerSnapshot>("GET", "/time_left", None) {
+                Ok(snapshot) => snapshot.time_left_secs.min(MAX_EXIT_SECS) as i32,
+                Err(err) => {
+                    eprintln!("Error: {err:?}");
+                    EXIT_SECS_ERROR
+                }
+            };
+            std::process::exit(code);
+        }
+        Command::Status {
+            raw_json: true,
+            names,
+            ..
+        } => {
+            let path = if names.is_empty() {
+                "/time_left".to_string()
+            } else {
+                format!("/time_left?names={}", names.join(","))
+            };
+            let (status, body) = client.send_raw("GET", &path, None)?;
+            if !status.is_success() {
+                return Err(ServerError::new(status, body).into());
+            }
+            out!("{body}")?;
+        }
+        Command::Status {
+            json_stream: true,
+            every,
+            warn_threshold,
+            output_file,
+            ..
+        } => {
+            let mut out: Box<dyn Write> = match output_file.filter(|path| !is_stdout(path)) {
+                Some(path) => Box::new(
+                    std::fs::OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(&path)
+                        .with_context(|| format!("failed to open {}", path.display()))?,
+                ),
+                None => Box::new(std::io::stdout().lock()),
+            };
+            loop {
+                let snapshot = client
+                    .send::<TimerSnapshot>("GET", "/time_left", None)?
+                    .with_low_time_warning(warn_threshold);
+                let line =
+                    serde_json::to_string(&snapshot).context("failed to encode JSON output")?;
+                match writeln!(out, "{line}").and_then(|()| out.flush()) {
+                    Ok(()) => {}
+                    // The reader (`head`, a FIFO consumer) went away; nobody is
+                    // left to stream to.
+                    Err(err) if err.kind() == ErrorKind::BrokenPipe => break,
+                    Err(err) => return Err(err).context("failed writing output"),
+                }
+                if !snapshot.running && snapshot.time_left_secs == 0 {
+                    break;
+                }
+                std::thread::sleep(Duration::from_secs(every));
+            }
+        }
+        Command::Status { all_sockets, .. } if !all_sockets.is_empty() => {
+            outln!("{}", socket_summary(&client, &all_sockets))?;
+        }
+        Command::Status {
+            json,
+            names,
+            warn_threshold,
+            no_validate,
+            no_warn,
+            group_digits,
+            ..
+        } if !names.is_empty() => {
+            let path = format!("/time_left?names={}", names.join(","));
+            let snapshots: BTreeMap<String, TimerSnapshot> = client
+                .send::<BTreeMap<String, TimerSnapshot>>("GET", &path, None)?
+                .into_iter()
+                .map(|(name, snapshot)| (name, snapshot.with_low_time_warning(warn_threshold)))
+                .collect();
+            if let Some(snapshot) = snapshots.values().next() {
+                snapshot.warn_if_newer_schema();
+            }
+            if !no_validate {
+                snapshots
+                    .values()
+                    .for_each(TimerSnapshot::warn_if_inconsistent);
+            }
+            if !no_warn && !json {
+                snapshots
+                    .values()
+                    .for_each(TimerSnapshot::warn_if_wide_hours);
+            }
+            if json {
+                outln!(
+                    "{}",
+                    serde_json::to_string(&snapshots).context("failed to encode JSON output")?
+                )?;
+            } else {
+                for (name, snapshot) in &snapshots {
+                    if group_digits {
+                        outln!("{name}: {snapshot:#}")?;
+                    } else {
+                        outln!("{name}: {snapshot}")?;
+                    }
+                }
+            }
+        }
+        Command::Status {
+            json,
+            ps1,
+            format,
+            output_file,
+            show_expiry,
+            warn_threshold,
+            expect_running,
+            expect_paused,
+            expect_expired,
+            quiet,
+            offline_as_idle,
+            no_validate,
+            no_warn,
+            count_down,
+            group_digits,
+            cached,
+            machine,
+            ..
+        } => {
+            // The width warning is only for the human-readable line.
+            let warn_wide = !no_warn
+                && !(quiet
+                    || ps1
+                    || json
+                    || count_down
+                    || machine
+                    || format.is_some()
+                    || output_file.as_deref().is_some_and(|path| !is_stdout(path)));
+            let mut cached_age = None;
+            let snapshot = match client.send::<TimerSnapshot>("GET", "/time_left", None) {
+                Ok(snapshot) => {
+                    snapshot.warn_if_newer_schema();
+                    if !no_validate {
+                        snapshot.warn_if_inconsistent();
+                    }
+                    if warn_wide {
+                        snapshot.warn_if_wide_hours();
+                    }
+                    let snapshot = snapshot.with_low_time_warning(warn_threshold);
+                    // Only a convenience for `--cached`; never fail the query over it.
+                    if let Err(err) = CachedSnapshot::store(&snapshot) {
+                        eprintln!("Warning: {err:#}");
+                    }
+                    snapshot
+                }
+                Err(err) if offline_as_idle && is_offline(&err) => TimerSnapshot::idle(),
+                Err(err) if cached && (is_offline(&err) || is_stale(&client, &err)) => {
+                    let entry = CachedSnapshot::load()
+                        .context("server is not running and there is no cached snapshot to show")?;
+                    cached_age = Some(entry.age_secs());
+                    entry.snapshot
+                }
+                Err(err) if is_stale(&client, &err) => {
+                    eprintln!(
+                        "[stale socket] – timersock may be dead at {}",
+                        client.socket.display()
+                    );
+                    std::process::exit(EXIT_STALE_SOCKET);
+                }
+                Err(err) => return Err(err),
+            };
+            if quiet {
+                // Only the `--expect-*` exit code matters.
+            } else if let Some(path) = output_file.filter(|path| !is_stdout(path)) {
+                let contents = match &format {
+                    Some(format) => format.render(&snapshot)?.into_bytes(),
+                    None => {
+                        serde_json::to_vec(&snapshot).context("failed to encode JSON output")?
+                    }
+                };
+                write_atomic(&path, &contents)?;
+            } else if ps1 {
+                print_ps1(&snapshot)?;
+            } else if count_down {
+                outln!("{}", snapshot.time_left_secs)?;
+            } else if machine {
+                outln!("{}", machine_row(&snapshot))?;
+            } else if let Some(format) = &format {
+                out!("{}", format.render(&snapshot)?)?;
+            } else if json {
+                if snapshot.running || snapshot.time_left_secs > 0 {
+                    outln!(
+                        "{}",
+                        serde_json::to_string(&snapshot).context("failed to encode JSON output")?
+                    )?;
+                }
+            } else {
+                let line = if group_digits {
+                    format!("{snapshot:#}")
+                } else {
+                    snapshot.to_string()
+                };
+                let line = match cached_age {
+                    Some(age) => format!("{line} [cached {age}s ago]"),
+                    None => line,
+                };
+                match snapshot.deadline_unix.filter(|_| show_expiry) {
+                    Some(deadline) => outln!("{line} expires_at={}", format_local_time(deadline)?)?,
+                    None => outln!("{line}")?,
+                }
+            }
+
+            let expected = [
+                (expect_running, TimerCondition::Running),
+                (expect_paused, TimerCondition::Paused),
+                (expect_expired, TimerCondition::Expired),
+            ]
+            .into_iter()
+            .find_map(|(requested, condition)| requested.then_some(condition));
+            let actual = TimerCondition::of(&snapshot);
+            if let Some(expected) = expected
+                && expected != actual
+            {
+                stdout_result(std::io::stdout().flush())?;
+                eprintln!("Expected timer to be {expected} but it is {actual}");
+                std::process::exit(EXIT_EXPECTATION_FAILED);
+            }
+        }
+        Command::Start {
+            seconds,
+            min_duration_warn,
+            no_warn,
+            repeat,
+            step,
+            dry_run: true,
+            ..
+        } => {
+            let seconds = match seconds {
+                Some(seconds) => seconds.to_seconds(unit)?,
+                None => project_default_duration()?,
+            };
+            warn_if_short(seconds, min_duration_warn, no_warn);
+            let total_secs = match repeat {
+                Some(cycles) => ramp_cycles(seconds, cycles, step, false)
+                    .steps
+                    .iter()
+                    .map(|step| step.seconds)
+                    .sum(),
+                None => seconds,
+            };
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .context("system clock is before the unix epoch")?
+                .as_secs();
+            let expiry = format_local_time(now.saturating_add(total_secs))?;
+            match repeat {
+                Some(cycles) => outln!(
+                    "Would start {cycles} cycles totalling {}, expiring at {expiry}",
+                    format_hms(total_secs)
+                )?,
+                None => outln!(
+                    "Would start {} timer, expiring at {expiry}",
+                    format_hms(total_secs)
+                )?,
+            }
+        }
+        Command::Start {
+            seconds,
+            verbose,
+            no_clobber,
+            force,
+            label,
+            min_duration_warn,
+            no_warn,
+            repeat,
+            step,
+            pause_at,
+            dry_run: false,
+        } => {
+            let seconds = match seconds {
+                Some(seconds) => seconds.to_seconds(unit)?,
+                None => project_default_duration()?,
+            };
+            warn_if_short(seconds, min_duration_warn, no_warn);
+            let no_clobber = no_clobber && !force;
+            let prefix = label.map_or_else(String::new, |label| format!("/timer/{label}"));
+            let snapshot: TimerSnapshot = match repeat {
+                Some(cycles) => {
+                    let body = ramp_cycles(seconds, cycles, step, no_clobber);
+                    client.send(
+                        "POST",
+                        &format!("{prefix}/interval"),
+                        Some(serde_json::to_string(&body)?),
+                    )?
+                }
+                None => {
+                    let body = StartBody {
+                        seconds,
+                        no_clobber,
+                        pause_at: pause_at.map(|at| at.to_seconds(unit)).transpose()?,
+                    };
+                    client.send(
+                        "POST",
+                        &format!("{prefix}/start"),
+                        Some(serde_json::to_string(&body)?),
+                    )?
+                }
+            };
+            outln!("{snapshot}")?;
+            if verbose && let Some(deadline) = snapshot.deadline_unix {
+                outln!("Timer will expire at {}", format_local_time(deadline)?)?;
+            }
+        }
+        Command::StartAligned { seconds, label } => {
+            let seconds = seconds.to_seconds(unit)?;
+            let since_epoch = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .context("system clock is before the unix epoch")?;
+            let delay = delay_to_next_minute(since_epoch);
+            let at = (since_epoch + delay).as_secs();
+            outln!("Starting at {}", format_local_time(at)?)?;
+            std::thread::sleep(delay);
+
+            let prefix = label.map_or_else(String::new, |label| format!("/timer/{label}"));
+            let body = StartBody {
+                seconds,
+                no_clobber: false,
+                pause_at: None,
+            };
+            let snapshot: TimerSnapshot = client.send(
+                "POST",
+                &format!("{prefix}/start"),
+                Some(serde_json::to_string(&body)?),
+            )?;
+            outln!("{snapshot}")?;
+        }
+        Command::Pause => {
+            let snapshot: TimerSnapshot = client.send("POST", "/pause", None)?;
+            outln!("{snapshot}")?;
+        }
+        Command::Resume => {
+            let snapshot: TimerSnapshot = client.send("POST", "/resume", None)?;
+            outln!("{snapshot}")?;
+        }
+        Command::Toggle => {
+            let snapshot: TimerSnapshot = client.send("POST", "/toggle", None)?;
+            outln!("{snapshot}")?;
+        }
+        Command::Extend {
+            seconds,
+            percent,
+            dry_run: true,
+            ..
+        } => {
+            let negative = seconds.is_some_and(|seconds| seconds.negative);
+            let seconds = resolve_seconds(
+                &client,
+                seconds.map(|seconds| seconds.duration),
+                percent,
+                unit,
+            )?;
+            let snapshot: TimerSnapshot = client.send("GET", "/time_left", None)?;
+            if negative {
+                let time_left = snapshot.time_left_secs.saturating_sub(seconds);
+                outln!(
+                    "Would reduce to {} (time_left_secs={time_left})",
+                    format_hms(time_left)
+                )?;
+            } else {
+                let time_left = snapshot.time_left_secs.saturating_add(seconds);
+                outln!(
+                    "Would extend to {} (time_left_secs={time_left})",
+                    format_hms(time_left)
+                )?;
+            }
+        }
+        Command::Extend {
+            seconds,
+            percent,
+            dry_run: false,
+            resume_on_extend,
+        } => {
+            let negative = seconds.is_some_and(|seconds| seconds.negative);
+            if negative && resume_on_extend {
+                bail!("--resume-on-extend cannot be used with a negative duration");
+            }
+            let seconds = resolve_seconds(
+                &client,
+                seconds.map(|seconds| seconds.duration),
+                percent,
+                unit,
+            )?;
+            if negative {
+                reduce(&client, seconds, false)?;
+            } else {
+                let body = ExtendBody {
+                    seconds,
+                    resume: resume_on_extend,
+                };
+                let snapshot: TimerSnapshot =
+                    client.send("POST", "/extend", Some(serde_json::to_string(&body)?))?;
+                outln!("{snapshot}")?;
+            }
+        }
+        Command::Reduce {
+            seconds,
+            percent,
+            dry_run: true,
+            ..
+        } => {
+            let seconds = resolve_seconds(&client, seconds, percent, unit)?;
+            let snapshot: TimerSnapshot = client.send("GET", "/time_left", None)?;
+            let time_left = snapshot.time_left_secs.saturating_sub(seconds);
+            outln!(
+                "Would reduce to {} (time_left_secs={time_left})",
+                format_hms(time_left)
+            )?;
+        }
+        Command::Reduce {
+            seconds,
+            percent,
+            force,
+            dry_run: false,
+        } => {
+            let seconds = resolve_seconds(&client, seconds, percent, unit)?;
+            reduce(&client, seconds, force)?;
+        }
+        Command::Diff { interval, json } => {
+            let before: TimerSnapshot = client.send("GET", "/time_left", None)?;
+            std::thread::sleep(Duration::from_secs(interval));
+            let after: TimerSnapshot = client.send("GET", "/time_left", None)?;
+
+            let diff = SnapshotDiff {
+                delta_secs: after.time_left_secs as i64 - before.time_left_secs as i64,
+                running_changed: after.running != before.running,
+                before,
+                after,
+            };
+            if json {
+                outln!(
+                    "{}",
+                    serde_json::to_string(&diff).context("failed to encode JSON output")?
+                )?;
+            } else {
+                outln!("before: {}", diff.before)?;
+                outln!("after: {}", diff.after)?;
+                outln!(
+                    "delta_secs={} running_changed={}",
+                    diff.delta_secs,
+                    diff.running_changed
+                )?;
+            }
+        }
+        Command::Interval { pattern } => {
+            let body = parse_interval_pattern(&pattern)?;
+            let snapshot: TimerSnapshot =
+                client.send("POST", "/interval", Some(serde_json::to_string(&body)?))?;
+            outln!("{snapshot}")?;
+        }
+        Command::Ping { count, json } => {
+            let mut samples_ms = Vec::new();
+            for _ in 0..count {
+                let started = Instant::now();
+                let (status, _) = client.send_raw("HEAD", "/healthz", None)?;
+                if !status.is_success() {
+                    bail!("server returned {status} for HEAD /healthz");
+                }
+                samples_ms.push(started.elapsed().as_secs_f64() * 1000.0);
+            }
+
+            let report = PingReport {
+                count: samples_ms.len(),
+                min_ms: samples_ms.iter().copied().fold(f64::INFINITY, f64::min),
+                avg_ms: samples_ms.iter().sum::<f64>() / samples_ms.len() as f64,
+                max_ms: samples_ms.iter().copied().fold(0.0, f64::max),
+                samples_ms,
+            };
+            if json {
+                outln!(
+                    "{}",
+                    serde_json::to_string(&report).context("failed to encode JSON output")?
+                )?;
+            } else {
+                outln!(
+                    "count={} min_ms={:.3} avg_ms={:.3} max_ms={:.3}",
+                    report.count,
+                    report.min_ms,
+                    report.avg_ms,
+                    report.max_ms
+                )?;
+            }
+        }
+        Command::Ready => {
+            let ready = client
+                .send_raw("HEAD", "/healthz", None)
+                .is_ok_and(|(status, _)| status.is_success());
+            if !ready {
+                std::process::exit(EXIT_NOT_READY);
+            }
+        }
+        Command::PauseAll { dry_run } => {
+            apply_to_all(&client, "pause", |snapshot| snapshot.running, dry_run)?;
+        }
+        Command::ResumeAll { dry_run } => {
+            apply_to_all(
+                &client,
+                "resume",
+                |snapshot| {
+                    !snapshot.running && (snapshot.time_left_secs > 0 || snapshot.overtime_secs > 0)
+                },
+                dry_run,
+            )?;
+        }
+        Command::Summary { json } => {
+            let timers: BTreeMap<String, TimerSnapshot> = client.send("GET", "/timers", None)?;
+            let summary = summarize(timers);
+            if json {
+                outln!(
+                    "{}",
+                    serde_json::to_string(&summary).context("failed to encode JSON output")?
+                )?;
+            } else {
+                out!(
+                    "total={} running={} paused={} finished={}",
+                    summary.total,
+                    summary.running,
+                    summary.paused,
+                    summary.finished
+                )?;
+                match &summary.soonest {
+                    Some(soonest) => outln!(
+                        " soonest={} soonest_time_left={}",
+                        soonest.name,
+                        soonest.time_left_hms
+                    )?,
+                    None => outln!()?,
+                }
+            }
+        }
+        Command::List {
+            json,
+            ndjson,
+            machine,
+        } => {
+            let timers: BTreeMap<String, TimerSnapshot> = client.send("GET", "/timers", None)?;
+            let listed: Vec<ListedTimer> = timers
+                .iter()
+                .map(|(name, snapshot)| ListedTimer { name, snapshot })
+                .collect();
+            if json {
+                outln!(
+                    "{}",
+                    serde_json::to_string(&listed).context("failed to encode JSON output")?
+                )?;
+            } else if ndjson {
+                for entry in &listed {
+                    outln!(
+                        "{}",
+                        serde_json::to_string(entry).context("failed to encode JSON output")?
+                    )?;
+                }
+            } else if machine {
+                let width = listed.iter().map(|entry| entry.name.len()).max();
+                for ListedTimer { name, snapshot } in &listed {
+                    outln!(
+                        "{name:<width$} {}",
+                        machine_row(snapshot),
+                        width = width.unwrap_or_default()
+                    )?;
+                }
+            } else {
+                for ListedTimer { name, snapshot } in listed {
+                    outln!("{name}: {snapshot}")?;
+                }
+            }
+        }
+        Command::Schedule {
+            action: Some(action),
+            at: Some(at),
+        } => {
+            let body = Scheduled {
+                action,
+                at_unix: parse_at(&at)?,
+            };
+            let snapshot: TimerSnapshot =
+                client.send("POST", "/schedule", Some(serde_json::to_string(&body)?))?;
+            outln!("{snapshot}")?;
+        }
+        Command::Schedule { .. } => {
+            let schedules: Vec<Scheduled> = client.send("GET", "/schedule", None)?;
+            for scheduled in &schedules {
+                outln!("action={} at_unix={}", scheduled.action, scheduled.at_unix)?;
+            }
+        }
+        Command::Cancel { action } => {
+            let response: CancelScheduleResponse = client.send(
+                "POST",
+                "/cancel_schedule",
+                Some(serde_json::to_string(&CancelScheduleBody { action })?),
+            )?;
+            if response.cancelled.is_empty() {
+                outln!("No pending actions to cancel")?;
+            }
+            for scheduled in &response.cancelled {
+                outln!(
+                    "cancelled action={} at_unix={}",
+                    scheduled.action,
+                    scheduled.at_unix
+                )?;
+            }
+        }
+        Command::Wait { below } => {
+            // Each long-poll has to finish within `--timeout`, so the server is
+            // told to answer a little before it runs out; then just ask again
+            // until the threshold is reached.
+            let poll = client.timeout.map_or(WAIT_POLL, |timeout| {
+                timeout
+                    .saturating_sub(WAIT_POLL_MARGIN.min(timeout / 2))
+                    .min(WAIT_POLL)
+            });
+            let path = format!("/wait?below={below}&timeout={:.3}", poll.as_secs_f64());
+            loop {
+                let response: WaitResponse = client.send("GET", &path, None)?;
+                if response.reached {
+                    outln!("{}", response.snapshot)?;
+                    break;
+                }
+            }
+        }
+        Command::Config {
+            action: None | Some(ConfigAction::Show),
+        } => {
+            let config: serde_json::Value = client.send("GET", "/config", None)?;
+            outln!(
+                "{}",
+                serde_json::to_string_pretty(&config).context("failed to encode JSON output")?
+            )?;
+        }
+        Command::Message { action: None } => {
+            let body: MessageBody = client.send("GET", "/message", None)?;
+            outln!("{}", body.message)?;
+        }
+        Command::Message {
+            action: Some(action),
+        } => {
+            let message = match action {
+                MessageAction::Set { text } => text,
+                MessageAction::Clear => String::new(),
+            };
+            let snapshot: TimerSnapshot = client.send(
+                "POST",
+                "/message",
+                Some(serde_json::to_string(&MessageBody { message })?),
+            )?;
+            outln!("{snapshot}")?;
+        }
+        Command::Bar { width, fill, empty } => {
+            let snapshot: TimerSnapshot = client.send("GET", "/time_left", None)?;
+            outln!("{}", render_bar(&snapshot, width.into(), fill, empty))?;
+        }
+        Command::Egg { doneness } => {
+            let body = StartBody {
+                seconds: doneness.seconds()?,
+                no_clobber: false,
+                pause_at: None,
+            };
+            let snapshot: TimerSnapshot = client.send(
+                "POST",
+                "/timer/egg/start",
+                Some(serde_json::to_string(&body)?),
+            )?;
+            outln!("{snapshot}")?;
+        }
+        Command::Parse { duration } => {
+            outln!("{}", parse_duration(&duration)?)?;
+        }
+        Command::Fmt { seconds } => {
+            outln!("{}", format_hms(seconds))?;
+            outln!("{}", format_duration(seconds))?;
+        }
+        Command::Share { label } => {
+            let prefix = label
+                .as_ref()
+                .map_or_else(String::new, |label| format!("/timer/{label}"));
+            let snapshot: TimerSnapshot =
+                client.send("GET", &format!("{prefix}/time_left"), None)?;
+            if snapshot.original_duration_secs == 0 {
+                bail!("timer has never been started, so there is nothing to share");
+            }
+            let shared = SharedTimer {
+                duration_secs: snapshot.original_duration_secs,
+                label,
+                message: snapshot.message,
+            };
+            outln!("{}", shared.encode()?)?;
+        }
+        Command::Load { code, no_clobber } => {
+            let shared = SharedTimer::decode(&code)?;
+            let prefix = shared
+                .label
+                .as_ref()
+                .map_or_else(String::new, |label| format!("/timer/{label}"));
+            let body = StartBody {
+                seconds: shared.duration_secs,
+                no_clobber,
+                pause_at: None,
+            };
+            let mut snapshot: TimerSnapshot = client.send(
+                "POST",
+                &format!("{prefix}/start"),
+                Some(serde_json::to_string(&body)?),
+            )?;
+            if !shared.message.is_empty() {
+                let body = MessageBody {
+                    message: shared.message,
+                };
+                snapshot = client.send(
+                    "POST",
+                    &format!("{prefix}/message"),
+                    Some(serde_json::to_string(&body)?),
+                )?;
+            }
+            outln!("{snapshot}")?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Turns `--percent` into seconds using the timer's original duration, which
+/// requires a round-trip to read the current snapshot first.
+fn resolve_seconds(
+    client: &TimerClient,
+    seconds: Option<DurationArg>,
+    percent: Option<u64>,
+    unit: DurationUnit,
+) -> Result<u64> {
+    let Some(percent) = percent else {
+        return seconds.map_or(Ok(0), |seconds| seconds.to_seconds(unit));
+    };
+
+    let snapshot: TimerSnapshot = client.send("GET", "/time_left", None)?;
+    if snapshot.original_duration_secs == 0 {
+        bail!("timer has no original duration; start it before using --percent");
+    }
+
+    let seconds = snapshot.original_duration_secs.saturating_mul(percent) / 100;
+    outln!(
+        "computed_secs={seconds} percent={percent} original_duration_secs={}",
+        snapshot.original_duration_secs
+    )?;
+    Ok(seconds)
+}
+
+/// Sends `POST /reduce` and explains any clamping.
+fn reduce(client: &TimerClient, seconds: u64, force: bool) -> Result<()> {
+    let response: ReduceResponse = client.send(
+        "POST",
+        "/reduce",
+        Some(serde_json::to_string(&ReduceBody { seconds, force })?),
+    )?;
+    outln!("{}", response.snapshot)?;
+    if response.clamped && response.snapshot.time_left_secs > 0 {
+        outln!(
+            "Reduced by {} of the requested {seconds} seconds: clamped at the server's minimum of {} seconds (use --force to go lower)",
+            response.reduced_by_secs,
+            response.snapshot.time_left_secs
+        )?;
+    } else if response.clamped {
+        outln!(
+            "Reduced by {} of the requested {seconds} seconds: the timer ran out",
+            response.reduced_by_secs
+        )?;
+    }
+    Ok(())
+}
+
+/// Parses `<duration> <label>, ... [x<reps>]` into the expanded list of steps,
+/// e.g. `30s work, 15s rest x8` becomes 16 alternating steps.
+fn parse_interval_pattern(pattern: &str) -> Result<IntervalBody> {
+    let pattern = pattern.trim();
+    let (segments, total_reps) = match pattern.rsplit_once(" x") {
+        Some((segments, reps)) if !reps.is_empty() && reps.bytes().all(|b| b.is_ascii_digit()) => {
+            let reps = reps
+                .parse::<u32>()
+                .with_context(|| format!("invalid repetition count `x{reps}`"))?;
+            (segments, reps)
+        }
+        _ => (pattern, 1),
+    };
+    if total_reps == 0 {
+        bail!("repetition count must be at least 1");
+    }
+
+    let mut segment_steps = Vec::new();
+    for segment in segments.split(',').map(str::trim) {
+        let Some((duration, label)) = segment.split_once(char::is_whitespace) else {
+            bail!("segment `{segment}` must look like `<duration> <label>`, e.g. `30s work`");
+        };
+        let label = label.trim();
+        if label.is_empty() {
+            bail!("segment `{segment}` is missing a label");
+        }
+        segment_steps.push((label.to_string(), parse_duration(duration)?));
+    }
+
+    let steps = (1..=total_reps)
+        .flat_map(|rep| {
+            segment_steps
+                .iter()
+                .map(move |(label, seconds)| IntervalStep {
+                    label: label.clone(),
+                    seconds: *seconds,
+                    rep,
+                })
+        })
+        .collect();
+    Ok(IntervalBody {
+        steps,
+        total_reps,
+        no_clobber: false,
+    })
+}
+
+/// Reads `default_duration` from the nearest `.timerrc`, looking in the
+/// current directory and then each parent up to `$HOME`.
+fn project_default_duration() -> Result<u64> {
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    let cwd = std::env::current_dir().context("failed to read the current directory")?;
+    for dir in cwd.ancestors() {
+        let path = dir.join(".timerrc");
+        if path.is_file() {
+            let contents = std::fs::read_to_string(&path)
+                .with_context(|| format!("failed to read {}", path.display()))?;
+            let duration = rc_string(&contents, "default_duration")
+                .with_context(|| format!("invalid {}", path.display()))?
+                .with_context(|| format!("{} has no default_duration", path.display()))?;
+            return parse_duration(&duration)
+                .with_context(|| format!("invalid default_duration in {}", path.display()));
+        }
+        if home.as_deref() == Some(dir) {
+            break;
+        }
+    }
+    bail!("no duration given and no .timerrc with a default_duration found")
+}
+
+/// Looks up a top-level `key = "value"` in `.timerrc`. Only this much of TOML
+/// is understood: comments, tables (whose keys are skipped) and basic strings
+/// without escapes.
+fn rc_string(contents: &str, key: &str) -> Result<Option<String>> {
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            break;
+        }
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((name, value)) = line.split_once('=') else {
+            bail!("line {}: expected `key = \"value\"`", number + 1);
+        };
+        if name.trim() != key {
+            continue;
+        }
+        let value = value.trim();
+        let value = value
+            .split_once(" #")
+            .map_or(value, |(value, _comment)| value.trim_end());
+        return match value
+            .strip_prefix('"')
+            .and_then(|value| value.strip_suffix('"'))
+        {
+            Some(value) if !value.contains(['"', '\\']) => Ok(Some(value.to_string())),
+            _ => bail!("line {}: {key} must be a quoted string", number + 1),
+        };
+    }
+    Ok(None)
+}
+
+fn default_schema_version() -> u32 {
+    1
+}
+
+fn unix_now() -> Result<u64> {
+    Ok(SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .context("system clock is before the unix epoch")?
+        .as_secs())
+}
+
+/// Resolves `--at` to a unix timestamp. Clock times are taken as today in the
+/// local timezone; if that is already past, the server applies it immediately.
+fn parse_at(text: &str) -> Result<u64> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .context("system clock is before the unix epoch")?
+        .as_secs();
+    if let Some(offset) = text.strip_prefix('+') {
+        return Ok(now.saturating_add(parse_duration(offset)?));
+    }
+
+    let parts = text
+        .split(':')
+        .map(|part| part.parse::<i32>())
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("invalid time `{text}`; expected HH:MM[:SS] or +<duration>"))?;
+    let (hour, minute, second) = match parts[..] {
+        [hour, minute] => (hour, minute, 0),
+        [hour, minute, second] => (hour, minute, second),
+        _ => bail!("invalid time `{text}`; expected HH:MM[:SS] or +<duration>"),
+    };
+    if !(0..24).contains(&hour) || !(0..60).contains(&minute) || !(0..60).contains(&second) {
+        bail!("time `{text}` is out of range");
+    }
+
+    let mut tm = local_tm(now)?;
+    tm.tm_hour = hour;
+    tm.tm_min = minute;
+    tm.tm_sec = second;
+    tm.tm_isdst = -1;
+    // SAFETY: `tm` was filled in by `localtime_r` above.
+    let at = unsafe { libc::mktime(&mut tm) };
+    u64::try_from(at).with_context(|| format!("failed to resolve local time `{text}`"))
+}
+
+/// Only accepts zones present in the system tz database, since libc
+/// silently falls back to UTC for names it cannot find.
+fn parse_timezone(value: &str) -> Result<String, String> {
+    let valid_name = !value.is_empty()
+        && !value.starts_with('/')
+        && value
+            .split('/')
+            .all(|part| !part.is_empty() && part != "..");
+    let zoneinfo = std::env::var_os("TZDIR")
+        .map_or_else(|| PathBuf::from("/usr/share/zoneinfo"), PathBuf::from);
+    if valid_name && zoneinfo.join(value).is_file() {
+        Ok(value.to_string())
+    } else {
+        Err(format!(
+            "unknown timezone `{value}`; expected an IANA name like `Europe/Berlin`"
+        ))
+    }
+}
+
+/// Points every later `local_tm` and `mktime` call at `tz`. libc reads `TZ`
+/// on first use, so this has to run before any time is formatted.
+fn use_timezone(tz: &str) {
+    // SAFETY: called at startup before any other thread exists, so nothing
+    // reads the environment concurrently.
+    unsafe { std::env::set_var("TZ", tz) };
+}
+
+fn local_tm(unix_secs: u64) -> Result<libc::tm> {
+    let time = libc::time_t::try_from(unix_secs).context("timestamp is out of range")?;
+    // SAFETY: `tm` is plain old data, so an all-zero value is valid, and both
+    // pointers passed to `localtime_r` refer to live locals.
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    if unsafe { libc::localtime_r(&time, &mut tm) }.is_null() {
+        bail!("failed to read the local time");
+    }
+    Ok(tm)
+}
+
+/// Formats a unix timestamp as `HH:MM:SS` in the local timezone, or `--tz`.
+fn format_local_time(unix_secs: u64) -> Result<String> {
+    let tm = local_tm(unix_secs)?;
+    Ok(format!(
+        "{:02}:{:02}:{:02}",
+        tm.tm_hour, tm.tm_min, tm.tm_sec
+    ))
+}
+
+fn parse_status_format(value: &str) -> Result<StatusFormat, String> {
+    match value {
+        "json-compact-secs" => Ok(StatusFormat::JsonCompactSecs),
+        _ => match value.strip_prefix('@') {
+            Some(path) if !path.is_empty() => Ok(StatusFormat::Template(PathBuf::from(path))),
+            _ => Err(format!(
+                "`{value}` is not a format; use `json-compact-secs` or `@<template path>`"
+            )),
+        },
+    }
+}
+
+/// Replaces each `{placeholder}` with the snapshot's value; `{{` and `}}`
+/// produce literal braces.
+fn render_template(template: &str, snapshot: &TimerSnapshot) -> Result<String> {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(index) = rest.find(['{', '}']) {
+        out.push_str(&rest[..index]);
+        let tail = &rest[index..];
+        if let Some(after) = tail.strip_prefix("{{") {
+            out.push('{');
+            rest = after;
+        } else if let Some(after) = tail.strip_prefix("}}") {
+            out.push('}');
+            rest = after;
+        } else if let Some(after) = tail.strip_prefix('{') {
+            let (name, after) = after
+                .split_once('}')
+                .context("unclosed `{` in template; write `{{` for a literal brace")?;
+            out.push_str(&template_value(name, snapshot)?);
+            rest = after;
+        } else {
+            bail!("unmatched `}}` in template; write `}}}}` for a literal brace");
+        }
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+fn template_value(name: &str, snapshot: &TimerSnapshot) -> Result<String> {
+    Ok(match name {
+        "time_left_hms" => snapshot.time_left_hms.clone(),
+        "time_left_secs" => snapshot.time_left_secs.to_string(),
+        "running" => snapshot.running.to_string(),
+        "alt" => snapshot.alt.clone(),
+        "original_duration_secs" => snapshot.original_duration_secs.to_string(),
+        "overtime_secs" => snapshot.overtime_secs.to_string(),
+        "total_running_secs" => snapshot.total_running_secs.to_string(),
+        "message" => snapshot.message.clone(),
+        "interval_label" => snapshot
+            .interval
+            .as_ref()
+            .map_or_else(String::new, |interval| interval.label.clone()),
+        _ => bail!(
+            "unknown placeholder `{{{name}}}`; expected one of {}",
+            TEMPLATE_PLACEHOLDERS.join(", ")
+        ),
+    })
+}
+
+fn parse_duration_arg(value: &str) -> Result<DurationArg, String> {
+    if let Ok(value) = value.parse::<u64>() {
+        return Ok(DurationArg::Bare(value));
+    }
+    parse_duration(value)
+        .map(DurationArg::Explicit)
+        .map_err(|err| format!("{err:#}"))
+}
+
+fn parse_signed_duration_arg(value: &str) -> Result<SignedDurationArg, String> {
+    let (negative, value) = match value.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, value),
+    };
+    parse_duration_arg(value).map(|duration| SignedDurationArg { negative, duration })
+}
+
+/// Labels end up in request paths and `?names=` lists, so they are limited to
+/// characters that need no escaping there.
+fn parse_label(value: &str) -> Result<String, String> {
+    if value.is_empty() {
+        return Err("label must not be empty".to_string());
+    }
+    match value
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')))
+    {
+        Some(invalid) => Err(format!(
+            "label may only contain ASCII letters, digits, '-', '_' and '.', found {invalid:?}"
+        )),
+        None => Ok(value.to_string()),
+    }
+}
+
+fn parse_timeout(text: &str) -> Result<Duration, String> {
+    match text.parse::<f64>().map(Duration::try_from_secs_f64) {
+        Ok(Ok(timeout)) if !timeout.is_zero() => Ok(timeout),
+        _ => Err(format!("`{text}` is not a positive number of seconds")),
+    }
+}
+
+/// `--output-file -` is the same as leaving the flag out.
+fn is_stdout(path: &Path) -> bool {
+    path == Path::new("-")
+}
+
+/// Time left until the next whole minute since the epoch; zero when already
+/// exactly on one.
+fn delay_to_next_minute(since_epoch: Duration) -> Duration {
+    const MINUTE_MS: u128 = 60_000;
+    let into_minute = since_epoch.as_millis() % MINUTE_MS;
+    if into_minute == 0 {
+        Duration::ZERO
+    } else {
+        Duration::from_millis((MINUTE_MS - into_minute) as u64)
+    }
+}
+
+/// Catches `start 5` typed when five minutes were meant.
+fn warn_if_short(seconds: u64, min_duration_warn: u64, no_warn: bool) {
+    if !no_warn && seconds > 0 && seconds < min_duration_warn {
+        eprintln!(
+            "Warning: starting timer for only {seconds} seconds. Did you mean {seconds}m ({}s)? Use --no-warn to suppress.",
+            seconds * 60
+        );
+    }
+}
+
+/// Expands `start --repeat` into an interval program whose cycles change by
+/// `step` seconds each, never going below one second.
+fn ramp_cycles(seconds: u64, cycles: u32, step: i64, no_clobber: bool) -> IntervalBody {
+    let steps = (0..cycles)
+        .map(|cycle| {
+            let offset = step.saturating_mul(i64::from(cycle));
+            let seconds = if offset < 0 {
+                seconds.saturating_sub(offset.unsigned_abs())
+            } else {
+                seconds.saturating_add(offset.unsigned_abs())
+            };
+            IntervalStep {
+                label: "cycle".to_string(),
+                seconds: seconds.max(1),
+                rep: cycle + 1,
+            }
+        })
+        .collect();
+    IntervalBody {
+        steps,
+        total_reps: cycles,
+        no_clobber,
+    }
+}
+
+/// Renders `1234567` as `1,234,567`.
+fn group_digits(value: u64) -> String {
+    let digits = value.to_string();
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+/// Parses a duration such as `90`, `90s`, `15m`, `1h` or `1h30m` into
+/// seconds. A bare number is seconds.
+fn parse_duration(text: &str) -> Result<u64> {
+    if text.is_empty() {
+        bail!("duration is empty");
+    }
+    if text.bytes().all(|byte| byte.is_ascii_digit()) {
+        return text
+            .parse::<u64>()
+            .with_context(|| format!("invalid duration `{text}`"));
+    }
+
+    let mut total = 0u64;
+    let mut rest = text;
+    while !rest.is_empty() {
+        let index = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let (digits, tail) = rest.split_at(index);
+        let value = digits
+            .parse::<u64>()
+            .with_context(|| format!("invalid duration `{text}`"))?;
+        let Some(unit) = tail.chars().next() else {
+            bail!("missing unit at the end of `{text}`; expected s, m or h");
+        };
+        let multiplier = match unit {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            _ => bail!("invalid duration unit in `{text}`; expected s, m or h"),
+        };
+        total = value
+            .checked_mul(multiplier)
+            .and_then(|secs| total.checked_add(secs))
+            .with_context(|| format!("duration `{text}` is too large"))?;
+        rest = &tail[unit.len_utf8()..];
+    }
+    Ok(total)
+}
+
+/// Inverse of `parse_duration`: `5400` becomes `1h30m`.
+fn format_duration(total_secs: u64) -> String {
+    let parts = [
+        (total_secs / 3600, 'h'),
+        ((total_secs % 3600) / 60, 'm'),
+        (total_secs % 60, 's'),
+    ];
+    let formatted: String = parts
+        .iter()
+        .filter(|(value, _)| *value > 0)
+        .map(|(value, unit)| format!("{value}{unit}"))
+        .collect();
+    if formatted.is_empty() {
+        "0s".to_string()
+    } else {
+        formatted
+    }
+}
+
+const BASE64URL_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// URL-safe base64 without padding, so share strings survive being pasted
+/// into URLs and shells unquoted.
+fn encode_base64url(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |group, (index, &byte)| {
+                group | u32::from(byte) << (16 - 8 * index)
+            });
+        for index in 0..=chunk.len() {
+            let sextet = (group >> (18 - 6 * index)) & 0x3f;
+            encoded.push(char::from(BASE64URL_ALPHABET[sextet as usize]));
+        }
+    }
+    encoded
+}
+
+fn decode_base64url(text: &str) -> Result<Vec<u8>> {
+    let text = text.trim_end_matches('=');
+    let mut decoded = Vec::with_capacity(text.len() * 3 / 4);
+    for chunk in text.as_bytes().chunks(4) {
+        if chunk.len() == 1 {
+            bail!("truncated input");
+        }
+        let mut group = 0u32;
+        for (index, &symbol) in chunk.iter().enumerate() {
+            let Some(sextet) = BASE64URL_ALPHABET.iter().position(|&c| c == symbol) else {
+                bail!("unexpected character {:?}", char::from(symbol));
+            };
+            group |= (sextet as u32) << (18 - 6 * index);
+        }
+        decoded.extend(group.to_be_bytes()[1..chunk.len()].iter());
+    }
+    Ok(decoded)
+}
+
+/// Connection settings shared by every request a command makes. The
+/// connection itself is kept open and reused across requests.
+#[derive(Debug)]
+struct TimerClient {
+    socket: PathBuf,
+    timeout: Option<Duration>,
+    no_wait: bool,
+    show_timing: bool,
+    connection: RefCell<Option<UnixStream>>,
+}
+
+impl TimerClient {
+    fn new(socket: PathBuf, timeout: Option<Duration>, no_wait: bool, show_timing: bool) -> Self {
+        Self {
+            socket,
+            timeout,
+            no_wait,
+            show_timing,
+            connection: RefCell::new(None),
+        }
+    }
+
+    fn send<T: for<'de> Deserialize<'de>>(
+        &self,
+        method: &str,
+        path: &str,
+        payload: Option<String>,
+    ) -> Result<T> {
+        let (status, body) = self.send_raw(method, path, payload)?;
+        if !status.is_success() {
+            return Err(ServerError::new(status, body).into());
+        }
+
+        let parsed = serde_json::from_str::<T>(&body).context("failed to parse JSON response")?;
+        Ok(parsed)
+    }
+
+    /// Performs the request and returns the status and body untouched, for
+    /// callers that must not depend on the response shape. `timeout` bounds the
+    /// whole exchange, not each individual read or write.
+    fn send_raw(
+        &self,
+        method: &str,
+        path: &str,
+        payload: Option<String>,
+    ) -> Result<(HttpStatus, String)> {
+        let deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+        // A HEAD response advertises the body's length but never sends it.
+        let has_body = method != "HEAD";
+
+        let body = payload.unwrap_or_default();
+        let content_header = if body.is_empty() {
+            String::new()
+        } else {
+            format!(
+                "Content-Type: application/json\r\nContent-Length: {}\r\n",
+                body.len()
+            )
+        };
+
+        let request =
+            format!("{method} {path} HTTP/1.1\r\nHost: localhost\r\n{content_header}\r\n{body}");
+
+        // An overloaded server says when to come back; follow that a few
+        // times, within `--timeout`, unless told not to wait.
+        let mut retries = 0;
+        loop {
+            let response = self.send_once(&request, has_body, deadline)?;
+            if matches!(response.status.code, 429 | 503)
+                && !self.no_wait
+                && retries < MAX_BUSY_RETRIES
+                && let Some(delay) = response.retry_after
+                && self.remaining(deadline)?.is_none_or(|left| delay < left)
+            {
+                retries += 1;
+                std::thread::sleep(delay);
+                continue;
+            }
+            return Ok((response.status, response.body));
+        }
+    }
+
+    fn send_once(
+        &self,
+        request: &str,
+        has_body: bool,
+        deadline: Option<Instant>,
+    ) -> Result<RawResponse> {
+        // The server may have closed an idle connection; that only shows up
+        // once we use it, so retry those on a fresh connection.
+        let reused = self.connection.borrow_mut().take();
+        if let Some(stream) = reused
+            && let Some(response) = self.exchange(stream, request, has_body, deadline)?
+        {
+            return Ok(response);
+        }
+
+        let stream = self.connect()?;
+        self.exchange(stream, request, has_body, deadline)?
+            .context("connection closed before a response was received")
+    }
+
+    /// Returns `Ok(None)` when the connection was closed before the request
+    /// could be sent or any part of a response arrived.
+    fn exchange(
+        &self,
+        mut stream: UnixStream,
+        request: &str,
+        has_body: bool,
+        deadline: Option<Instant>,
+    ) -> Result<Option<RawResponse>> {
+        stream
+            .set_write_timeout(self.remaining(deadline)?)
+            .context("failed setting write timeout")?;
+        match stream
+            .write_all(request.as_bytes())
+            .and_then(|()| stream.flush())
+        {
+            Ok(()) => {}
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    ErrorKind::BrokenPipe | ErrorKind::ConnectionReset
+                ) =>
+            {
+                return Ok(None);
+            }
+            Err(err) => return Err(self.timeout_error(err)).context("failed writing request"),
+        }
+
+        let mut response = Vec::new();
+        let header_end = loop {
+            if let Some(end) = response.windows(4).position(|window| window == b"\r\n\r\n") {
+                break end;
+            }
+            if self.read_chunk(&mut stream, &mut response, deadline)? == 0 {
+                if response.is_empty() {
+                    return Ok(None);
+                }
+                bail!("malformed HTTP response: missing body separator");
+            }
+        };
+
+        let head =
+            std::str::from_utf8(&response[..header_end]).context("response is not valid UTF-8")?;
+        let (status_line, headers) = head
+            .split_once("\r\n")
+            .context("malformed HTTP response: missing status line")?;
+        let status = parse_status_line(status_line)?;
+        if self.show_timing
+            && let Some(millis) = header_value(headers, "x-response-time-ms")
+        {
+            let request_line = request.lines().next().unwrap_or_default();
+            let target = request_line.trim_end_matches(" HTTP/1.1");
+            eprintln!("{target}: {millis} ms");
+        }
+        let content_length = if has_body {
+            header_value(headers, "content-length")
+                .map(|value| value.parse::<usize>())
+                .transpose()
+                .context("malformed HTTP response: invalid Content-Length")?
+        } else {
+            Some(0)
+        };
+        // Only the delay-seconds form; an HTTP date is ignored.
+        let retry_after = header_value(headers, "retry-after")
+            .and_then(|value| value.trim().parse::<u64>().ok())
+            .map(Duration::from_secs);
+        let closing = header_value(headers, "connection")
+            .is_some_and(|value| value.eq_ignore_ascii_case("close"));
+
+        let body_start = header_end + 4;
+        let body_end = match content_length {
+            Some(length) => {
+                while response.len() < body_start + length {
+                    if self.read_chunk(&mut stream, &mut response, deadline)? == 0 {
+                        bail!("connection closed before the response body was complete");
+                    }
+                }
+                body_start + length
+            }
+            None => {
+                while self.read_chunk(&mut stream, &mut response, deadline)? > 0 {}
+                response.len()
+            }
+        };
+        let body = String::from_utf8(response[body_start..body_end].to_vec())
+            .context("response is not valid UTF-8")?;
+
+        if content_length.is_some() && !closing {
+            *self.connection.borrow_mut() = Some(stream);
+        }
+        Ok(Some(RawResponse {
+            status,
+            body,
+            retry_after,
+        }))
+    }
+
+    fn read_chunk(
+        &self,
+        stream: &mut UnixStream,
+        response: &mut Vec<u8>,
+        deadline: Option<Instant>,
+    ) -> Result<usize> {
+        let mut chunk = [0; 4096];
+        stream
+            .set_read_timeout(self.remaining(deadline)?)
+            .context("failed setting read timeout")?;
+        let read = stream
+            .read(&mut chunk)
+            .map_err(|err| self.timeout_error(err))
+            .context("failed reading response")?;
+        response.extend_from_slice(&chunk[..read]);
+        Ok(read)
+    }
+
+    /// With `no_wait`, connecting fails straight away instead of blocking when
+    /// the server's accept queue is full.
+    fn connect(&self) -> Result<UnixStream> {
+        let context = || format!("failed to connect to socket {}", self.socket.display());
+        if !self.no_wait {
+            return UnixStream::connect(&self.socket).with_context(context);
+        }
+
+        let socket = Socket::new(Domain::UNIX, Type::STREAM, None).with_context(context)?;
+        let address = SockAddr::unix(&self.socket).with_context(context)?;
+        socket.set_nonblocking(true).with_context(context)?;
+        socket.connect(&address).with_context(context)?;
+        socket.set_nonblocking(false).with_context(context)?;
+        Ok(socket.into())
+    }
+
+    fn remaining(&self, deadline: Option<Instant>) -> Result<Option<Duration>> {
+        let Some(deadline) = deadline else {
+            return Ok(None);
+        };
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Err(TimeoutError(self.timeout.unwrap_or_default()).into());
+        }
+        Ok(Some(left))
+    }
+
+    fn timeout_error(&self, err: std::io::Error) -> anyhow::Error {
+        match err.kind() {
+            ErrorKind::WouldBlock | ErrorKind::TimedOut => {
+                TimeoutError(self.timeout.unwrap_or_default()).into()
+            }
+            _ => err.into(),
+        }
+    }
+}
+
+/// The whole request did not finish within `--timeout`.
+#[derive(Debug)]
+struct TimeoutError(Duration);
+
+impl fmt::Display for TimeoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "request timed out after {:?}", self.0)
+    }
+}
+
+impl std::error::Error for TimeoutError {}
+
+/// The server answered with a non-2xx status.
+#[derive(Debug, PartialEq, Eq)]
+struct ServerError {
+    status: HttpStatus,
+    body: String,
+    code: Option<ErrorCode>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ErrorCode {
+    NotFound,
+    StateConflict,
+    ValidationError,
+    Internal,
+}
+
+impl ServerError {
+    fn new(status: HttpStatus, body: String) -> Self {
+        #[derive(Deserialize)]
+        struct ErrorBody {
+            error_code: ErrorCode,
+        }
+
+        // Older servers and unknown codes just fall back to the generic exit.
+        let code = serde_json::from_str::<ErrorBody>(&body)
+            .ok()
+            .map(|body| body.error_code);
+        Self { status, body, code }
+    }
+
+    fn exit_code(&self) -> Option<i32> {
+        self.code.map(|code| match code {
+            ErrorCode::NotFound => EXIT_NOT_FOUND,
+            ErrorCode::StateConflict => EXIT_STATE_CONFLICT,
+            ErrorCode::ValidationError => EXIT_VALIDATION_ERROR,
+            ErrorCode::Internal => EXIT_INTERNAL,
+        })
+    }
+}
+
+impl fmt::Display for ServerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "server returned {}: {}", self.status, self.body)
+    }
+}
+
+impl std::error::Error for ServerError {}
+
+/// Whether connecting failed because no server is there, as opposed to a
+/// server that misbehaves or is too slow.
+fn is_offline(err: &anyhow::Error) -> bool {
+    err.root_cause()
+        .downcast_ref::<std::io::Error>()
+        .is_some_and(|err| {
+            matches!(
+                err.kind(),
+                ErrorKind::NotFound | ErrorKind::ConnectionRefused
+            )
+        })
+}
+
+/// The socket file is there but nothing answers on it: connections are
+/// refused, or accepted and never answered. Typical of a server that crashed
+/// without cleaning up.
+fn is_stale(client: &TimerClient, err: &anyhow::Error) -> bool {
+    let root = err.root_cause();
+    let refused = root
+        .downcast_ref::<std::io::Error>()
+        .is_some_and(|err| err.kind() == ErrorKind::ConnectionRefused);
+    (refused || root.is::<TimeoutError>()) && client.socket.exists()
+}
+
+fn header_value<'a>(headers: &'a str, name: &str) -> Option<&'a str> {
+    headers.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        key.trim().eq_ignore_ascii_case(name).then(|| value.trim())
+    })
+}
+
+#[derive(Debug)]
+struct RawResponse {
+    status: HttpStatus,
+    body: String,
+    retry_after: Option<Duration>,
+}
+
+/// The status line of a response, e.g. `404 Not Found`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct HttpStatus {
+    code: u16,
+    reason: String,
+}
+
+impl HttpStatus {
+    fn is_success(&self) -> bool {
+        (200..300).contains(&self.code)
+    }
+}
+
+impl fmt::Display for HttpStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.reason.is_empty() {
+            write!(f, "{}", self.code)
+        } else {
+            write!(f, "{} {}", self.code, self.reason)
+        }
+    }
+}
+
+/// The reason phrase is optional in HTTP/1.1, so it may come back empty.
+fn parse_status_line(status_line: &str) -> Result<HttpStatus> {
+    let mut parts = status_line.splitn(3, ' ');
+    let _http_version = parts
+        .next()
+        .filter(|version| !version.is_empty())
+        .context("malformed status line: missing HTTP version")?;
+    let code = parts
+        .next()
+        .context("malformed status line: missing status code")?
+        .parse::<u16>()
+        .context("malformed status line: invalid status code")?;
+    let reason = parts.next().unwrap_or_default().trim().to_string();
+    Ok(HttpStatus { code, reason })
+}
+
+/// Aggregates the `/timers` listing; only running timers can be "soonest".
+fn summarize(timers: BTreeMap<String, TimerSnapshot>) -> TimersSummary {
+    let mut summary = TimersSummary {
+        total: timers.len(),
+        ..TimersSummary::default()
+    };
+    for (name, snapshot) in timers {
+        if snapshot.time_left_secs == 0 {
+            summary.finished += 1;
+        } else if snapshot.running {
+            summary.running += 1;
+            if summary
+                .soonest
+                .as_ref()
+                .is_none_or(|soonest| snapshot.time_left_secs < soonest.time_left_secs)
+            {
+                summary.soonest = Some(SoonestTimer {
+                    name,
+                    time_left_secs: snapshot.time_left_secs,
+                    time_left_hms: snapshot.time_left_hms,
+                });
+            }
+        } else {
+            summary.paused += 1;
+        }
+    }
+    summary
+}
+
+/// Sends `POST /timer/<name>/<action>` to every timer matching `select`, each
+/// on its own thread and connection. One failure does not stop the others; the
+/// command fails afterwards if any did.
+fn apply_to_all(
+    client: &TimerClient,
+    action: &str,
+    select: impl Fn(&TimerSnapshot) -> bool,
+    dry_run: bool,
+) -> Result<()> {
+    let timers: BTreeMap<String, TimerSnapshot> = client.send("GET", "/timers", None)?;
+    let names: Vec<String> = timers
+        .into_iter()
+        .filter(|(_, snapshot)| select(snapshot))
+        .map(|(name, _)| name)
+        .collect();
+
+    if dry_run {
+        for name in &names {
+            outln!("{name}: would {action}")?;
+        }
+        return Ok(());
+    }
+
+    let results: Vec<Result<TimerSnapshot>> = std::thread::scope(|scope| {
+        let handles: Vec<_> = names
+            .iter()
+            .map(|name| {
+                let client = TimerClient::new(
+                    client.socket.clone(),
+                    client.timeout,
+                    client.no_wait,
+                    client.show_timing,
+                );
+                let path = format!("/timer/{name}/{action}");
+                scope.spawn(move || client.send::<TimerSnapshot>("POST", &path, None))
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|_| Err(anyhow!("{action} thread panicked")))
+            })
+            .collect()
+    });
+
+    let mut failed = 0;
+    for (name, result) in names.iter().zip(results) {
+        match result {
+            Ok(snapshot) => outln!("{name}: {snapshot}")?,
+            Err(err) => {
+                failed += 1;
+                outln!("{name}: ERROR ({err:#})")?;
+            }
+        }
+    }
+    if failed > 0 {
+        bail!("failed to {action} {failed} of {} timers", names.len());
+    }
+    Ok(())
+}
+
+/// Queries every socket on its own thread; failures are reported inline so
+/// one unreachable server does not hide the others.
+fn socket_summary(client: &TimerClient, sockets: &[PathBuf]) -> String {
+    let results: Vec<Result<TimerSnapshot>> = std::thread::scope(|scope| {
+        let handles: Vec<_> = sockets
+            .iter()
+            .map(|socket| {
+                let client = TimerClient::new(
+                    socket.clone(),
+                    client.timeout,
+                    client.no_wait,
+                    client.show_timing,
+                );
+                scope.spawn(move || client.send::<TimerSnapshot>("GET", "/time_left", None))
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|_| Err(anyhow!("query thread panicked")))
+            })
+            .collect()
+    });
+
+    sockets
+        .iter()
+        .zip(results)
+        .map(|(socket, result)| {
+            let name = socket.file_stem().map_or_else(
+                || socket.display().to_string(),
+                |stem| stem.to_string_lossy().into_owned(),
+            );
+            match result {
+                Ok(snapshot) => {
+                    let state = if snapshot.time_left_secs == 0 {
+                        "EXPIRED"
+                    } else if snapshot.running {
+                        "RUNNING"
+                    } else {
+                        "PAUSED"
+                    };
+                    format!(
+                        "{name}: {:02}:{:02} {state}",
+                        snapshot.time_left_secs / 60,
+                        snapshot.time_left_secs % 60
+                    )
+                }
+                Err(err) => format!("{name}: ERROR ({err:#})"),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" | ")
+}
+
+/// Writes to a temporary file next to `path` and renames it into place, so
+/// readers never observe a partially written file.
+fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    let file_name = path
+        .file_name()
+        .with_context(|| format!("output path {} has no file name", path.display()))?;
+    let mut temp_name = OsString::from(".");
+    temp_name.push(file_name);
+    temp_name.push(format!(".{}.tmp", std::process::id()));
+    let temp_path = path.with_file_name(temp_name);
+
+    std::fs::write(&temp_path, contents)
+        .with_context(|| format!("failed writing {}", temp_path.display()))?;
+    if let Err(err) = std::fs::rename(&temp_path, path) {
+        let _ = std::fs::remove_file(&temp_path);
+        return Err(err).with_context(|| {
+            format!(
+                "failed renaming {} to {}",
+                temp_path.display(),
+                path.display()
+            )
+        });
+    }
+    Ok(())
+}
+
+fn format_hms(total_secs: u64) -> String {
+    let hours = total_secs / 3600;
+    let minutes = (total_secs % 3600) / 60;
+    let seconds = total_secs % 60;
+    format!("{hours:02}:{minutes:02}:{seconds:02}")
+}
+
+/// Formats seconds as `m:ss`, or `h:mm:ss` once the timer exceeds an hour.
+fn format_hms_short(total_secs: u64) -> String {
+    let hours = total_secs / 3600;
+    let minutes = (total_secs % 3600) / 60;
+    let seconds = total_secs % 60;
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{seconds:02}")
+    } else {
+        format!("{minutes}:{seconds:02}")
+    }
+}
+
+/// Pads each field to a fixed width so rows line up without a header.
+fn machine_row(snapshot: &TimerSnapshot) -> String {
+    format!(
+        "{:>8} {:>6} {:<5}",
+        snapshot.time_left_hms, snapshot.time_left_secs, snapshot.running
+    )
+}
+
+/// Fills cells in proportion to the elapsed share of the original duration.
+/// A timer that was never given a duration has no progress to show, so its
+/// bar is all `?`.
+fn render_bar(snapshot: &TimerSnapshot, width: usize, fill: char, empty: char) -> String {
+    let cells = if snapshot.original_duration_secs == 0 {
+        "?".repeat(width)
+    } else {
+        let total = u128::from(snapshot.original_duration_secs);
+        let elapsed = total.saturating_sub(u128::from(snapshot.time_left_secs));
+        let filled = (elapsed * width as u128 / total) as usize;
+        let mut cells = String::with_capacity(width);
+        cells.extend(std::iter::repeat_n(fill, filled));
+        cells.extend(std::iter::repeat_n(empty, width - filled));
+        cells
+    };
+    format!("[{cells}]")
+}
+
+fn print_ps1(snapshot: &TimerSnapshot) -> Result<()> {
+    if snapshot.time_left_secs == 0 {
+        return Ok(());
+    }
+
+    let icon = if snapshot.running { '⏱' } else { '⏸' };
+    out!(" {icon}{}", format_hms_short(snapshot.time_left_secs))
+}
+
+/// Treats a reader that went away (`timer list | head`) as a clean exit, since
+/// nobody is left to read the rest.
+fn stdout_result(result: std::io::Result<()>) -> Result<()> {
+    match result {
+        Ok(()) => Ok(()),
+        Err(err) if err.kind() == ErrorKind::BrokenPipe => std::process::exit(0),
+        Err(err) => Err(err).context("failed writing to stdout"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(time_left_secs: u64, running: bool) -> TimerSnapshot {
+        TimerSnapshot {
+            time_left_secs,
+            time_left_hms: format_hms(time_left_secs),
+            running,
+            ..TimerSnapshot::idle()
+        }
+    }
+
+    fn timers(entries: &[(&str, u64, bool)]) -> BTreeMap<String, TimerSnapshot> {
+        entries
+            .iter()
+            .map(|&(name, secs, running)| (name.to_string(), snapshot(secs, running)))
+            .collect()
+    }
+
+    #[test]
+    fn template_fills_several_placeholders_and_literal_braces() {
+        let snapshot = TimerSnapshot {
+            alt: "paused".to_string(),
+            message: "Tea".to_string(),
+            ..snapshot(90, false)
+        };
+        let rendered = render_template(
+            "{alt} {time_left_hms} ({time_left_secs}s, running={running}) {{{message}}}",
+            &snapshot,
+        )
+        .unwrap();
+        assert_eq!(rendered, "paused 00:01:30 (90s, running=false) {Tea}");
+
+        assert!(render_template("{nope}", &snapshot).is_err());
+        assert!(render_template("{alt", &snapshot).is_err());
+        assert!(render_template("alt}", &snapshot).is_err());
+    }
+
+    fn ramp_seconds(body: &IntervalBody) -> Vec<u64> {
+        body.steps.iter().map(|step| step.seconds).collect()
+    }
+
+    #[test]
+    fn ramp_over_three_cycles() {
+        let body = ramp_cycles(300, 3, 60, false);
+        assert_eq!(ramp_seconds(&body), [300, 360, 420]);
+        assert_eq!(body.total_reps, 3);
+        let reps: Vec<u32> = body.steps.iter().map(|step| step.rep).collect();
+        assert_eq!(reps, [1, 2, 3]);
+
+        let body = ramp_cycles(300, 3, -120, true);
+        assert_eq!(ramp_seconds(&body), [300, 180, 60]);
+        assert!(body.no_clobber);
+
+        // Shortened cycles bottom out at one second.
+        assert_eq!(ramp_seconds(&ramp_cycles(100, 3, -90, false)), [100, 10, 1]);
+    }
+
+    #[test]
+    fn delay_runs_to_the_next_whole_minute() {
+        let at = |secs: u64, millis: u64| Duration::from_secs(secs) + Duration::from_millis(millis);
+        assert_eq!(delay_to_next_minute(at(1_700_000_000, 0)), at(40, 0));
+        assert_eq!(delay_to_next_minute(at(1_700_000_019, 750)), at(20, 250));
+        assert_eq!(delay_to_next_minute(at(1_700_000_039, 999)), at(0, 1));
+    }
+
+    #[test]
+    fn delay_is_zero_exactly_on_a_minute_boundary() {
+        assert_eq!(
+            delay_to_next_minute(Duration::from_secs(1_700_000_040)),
+            Duration::ZERO
+        );
+        assert_eq!(delay_to_next_minute(Duration::ZERO), Duration::ZERO);
+    }
+
+    #[test]
+    fn share_string_round_trips() {
+        let shared = SharedTimer {
+            duration_secs: 1500,
+            label: Some("pomodoro".to_string()),
+            message: "Break time — stretch!".to_string(),
+        };
+        let code = shared.encode().unwrap();
+        assert!(
+            code.bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_'),
+            "{code}"
+        );
+
+        let loaded = SharedTimer::decode(&format!("  {code}\n")).unwrap();
+        assert_eq!(loaded.duration_secs, 1500);
+        assert_eq!(loaded.label.as_deref(), Some("pomodoro"));
+        assert_eq!(loaded.message, "Break time — stretch!");
+    }
+
+    #[test]
+    fn base64url_round_trips_every_length() {
+        let bytes: Vec<u8> = (0..=255).collect();
+        for len in 0..bytes.len() {
+            let encoded = encode_base64url(&bytes[..len]);
+            assert_eq!(decode_base64url(&encoded).unwrap(), &bytes[..len]);
+        }
+        assert!(decode_base64url("not base64!").is_err());
+    }
+
+    #[test]
+    fn group_digits_across_magnitudes() {
+        assert_eq!(group_digits(0), "0");
+        assert_eq!(group_digits(999), "999");
+        assert_eq!(group_digits(1_000), "1,000");
+        assert_eq!(group_digits(604_800), "604,800");
+        assert_eq!(group_digits(1_000_000), "1,000,000");
+        assert_eq!(group_digits(u64::MAX), "18,446,744,073,709,551,615");
+    }
+
+    #[test]
+    fn alternate_display_groups_second_counts() {
+        let snapshot = snapshot(1_000_000, true);
+        assert!(format!("{snapshot:#}").contains("1,000,000"));
+        assert!(!format!("{snapshot}").contains("1,000,000"));
+    }
+
+    fn bar_at(time_left_secs: u64, original_duration_secs: u64) -> String {
+        let snapshot = TimerSnapshot {
+            original_duration_secs,
+            ..snapshot(time_left_secs, true)
+        };
+        render_bar(&snapshot, 10, '#', '-')
+    }
+
+    #[test]
+    fn bar_at_zero_half_and_full_progress() {
+        assert_eq!(bar_at(600, 600), "[----------]");
+        assert_eq!(bar_at(300, 600), "[#####-----]");
+        assert_eq!(bar_at(0, 600), "[##########]");
+        assert_eq!(bar_at(0, 0), "[??????????]");
+    }
+
+    #[test]
+    fn formatted_durations_parse_back() {
+        for secs in [0, 1, 59, 60, 61, 3599, 3600, 3601, 5400, 86_399, 360_000] {
+            let formatted = format_duration(secs);
+            assert_eq!(parse_duration(&formatted).unwrap(), secs, "{formatted}");
+        }
+    }
+
+    #[test]
+    fn canonical_durations_format_back() {
+        for text in ["0s", "45s", "5m", "1h", "1h30m", "2h5s", "1h1m1s", "100h"] {
+            let secs = parse_duration(text).unwrap();
+            assert_eq!(format_duration(secs), text);
+        }
+        // Non-canonical spellings come back normalised.
+        assert_eq!(format_duration(parse_duration("90m").unwrap()), "1h30m");
+        assert_eq!(format_duration(parse_duration("30s1m").unwrap()), "1m30s");
+    }
+
+    #[test]
+    fn bare_numbers_default_to_seconds() {
+        assert_eq!(
+            DurationArg::Bare(90)
+                .to_seconds(DurationUnit::Seconds)
+                .unwrap(),
+            90
+        );
+    }
+
+    #[test]
+    fn bare_numbers_scale_to_minutes() {
+        assert_eq!(
+            DurationArg::Bare(90)
+                .to_seconds(DurationUnit::Minutes)
+                .unwrap(),
+            5400
+        );
+    }
+
+    #[test]
+    fn bare_numbers_scale_to_hours() {
+        assert_eq!(
+            DurationArg::Bare(2)
+                .to_seconds(DurationUnit::Hours)
+                .unwrap(),
+            7200
+        );
+        assert!(
+            DurationArg::Bare(u64::MAX)
+                .to_seconds(DurationUnit::Hours)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn explicit_durations_ignore_the_unit() {
+        for unit in [
+            DurationUnit::Seconds,
+            DurationUnit::Minutes,
+            DurationUnit::Hours,
+        ] {
+            assert_eq!(DurationArg::Explicit(90).to_seconds(unit).unwrap(), 90);
+        }
+    }
+
+    #[test]
+    fn snapshots_from_older_servers_still_parse() {
+        let parsed: TimerSnapshot = serde_json::from_str(
+            r#"{"time_left_secs": 5, "time_left_hms": "00:00:05", "alt": "default", "running": true}"#,
+        )
+        .unwrap();
+        assert_eq!(parsed.schema_version, 1);
+        assert_eq!(parsed.time_left_secs, 5);
+        assert!(parsed.running);
+        assert_eq!(parsed.interval, None);
+        assert_eq!(parsed.message, "");
+    }
+
+    #[test]
+    fn summary_of_no_timers() {
+        let summary = serde_json::to_value(summarize(timers(&[]))).unwrap();
+        assert_eq!(
+            summary,
+            serde_json::json!({
+                "total": 0, "running": 0, "paused": 0, "finished": 0, "soonest": null
+            })
+        );
+    }
+
+    #[test]
+    fn summary_of_one_running_timer() {
+        let summary = serde_json::to_value(summarize(timers(&[("tea", 90, true)]))).unwrap();
+        assert_eq!(
+            summary,
+            serde_json::json!({
+                "total": 1, "running": 1, "paused": 0, "finished": 0,
+                "soonest": {"name": "tea", "time_left_secs": 90, "time_left_hms": "00:01:30"}
+            })
+        );
+    }
+
+    #[test]
+    fn summary_of_several_timers_picks_the_soonest_running_one() {
+        let summary = summarize(timers(&[
+            ("pasta", 600, true),
+            ("tea", 90, true),
+            ("bread", 30, false),
+            ("egg", 0, false),
+        ]));
+        assert_eq!(summary.total, 4);
+        assert_eq!(summary.running, 2);
+        assert_eq!(summary.paused, 1);
+        assert_eq!(summary.finished, 1);
+        let soonest = summary.soonest.expect("a running timer");
+        assert_eq!(soonest.name, "tea");
+        assert_eq!(soonest.time_left_secs, 90);
+    }
 }
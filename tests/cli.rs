@@ -0,0 +1,276 @@
+mod common;
+
+use std::ffi::CString;
+use std::fs::{File, OpenOptions};
+use std::io::Read;
+use std::os::unix::ffi::OsStrExt;
+use std::process::Output;
+use std::time::{Duration, Instant};
+
+use common::Server;
+
+fn stdout(output: &Output) -> String {
+    assert!(
+        output.status.success(),
+        "timer failed with {}: {}",
+        output.status,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout.clone()).expect("utf-8 output")
+}
+
+#[test]
+fn json_compact_secs_prints_only_s_and_r() {
+    let server = Server::start(&[]);
+    server.post_json("/start", r#"{"seconds": 300}"#);
+    server.post_json("/pause", "");
+
+    let out = stdout(&server.timer(&["status", "--format", "json-compact-secs"]));
+    let line = out.strip_suffix('\n').expect("one line");
+    assert!(!line.contains(' '), "not compact: {line}");
+    let value: serde_json::Value = serde_json::from_str(line).expect("valid JSON");
+    let object = value.as_object().expect("an object");
+    assert_eq!(object.keys().collect::<Vec<_>>(), ["r", "s"]);
+    assert_eq!(object["r"], false);
+    let secs = object["s"].as_u64().expect("s is an unsigned integer");
+    assert!((299..=300).contains(&secs), "s={secs}");
+
+    // Unlike --json, a timer stopped at zero still prints.
+    server.post_json("/start", r#"{"seconds": 0}"#);
+    let out = stdout(&server.timer(&["status", "--format", "json-compact-secs"]));
+    assert_eq!(out, "{\"s\":0,\"r\":false}\n");
+}
+
+#[test]
+fn wait_resolves_immediately_when_already_below() {
+    let server = Server::start(&[]);
+    server.post_json("/start", r#"{"seconds": 60}"#);
+
+    let started = Instant::now();
+    let out = stdout(&server.timer(&["wait", "--below", "120"]));
+    assert!(started.elapsed() < Duration::from_secs(2));
+    assert!(out.contains("00:0"), "{out}");
+}
+
+#[test]
+fn wait_keeps_polling_past_a_short_timeout() {
+    let server = Server::start(&[]);
+    server.post_json("/start", r#"{"seconds": 3}"#);
+
+    // Each poll must come back before the 0.5s timeout, so this only passes
+    // if the CLI polls again instead of timing out.
+    let started = Instant::now();
+    let output = server.timer(&["--timeout", "0.5", "wait", "--below", "2"]);
+    stdout(&output);
+    assert!(started.elapsed() >= Duration::from_secs(1));
+}
+
+#[test]
+fn status_renders_a_template_file() {
+    let server = Server::start(&[]);
+    server.post_json("/start", r#"{"seconds": 90}"#);
+    server.post_json("/pause", "");
+    server.post_json("/message", r#"{"message": "Tea"}"#);
+
+    let template = server.dir.path().join("status.tmpl");
+    std::fs::write(
+        &template,
+        "{alt}|{original_duration_secs}|{running}|{message}|{interval_label}\n",
+    )
+    .unwrap();
+    let format = format!("@{}", template.display());
+
+    let out = stdout(&server.timer(&["status", "--format", &format]));
+    assert_eq!(out, "paused|90|false|Tea|\n");
+}
+
+#[test]
+fn list_json_is_one_array_with_every_timer() {
+    let server = Server::start(&[]);
+    for (name, seconds) in [("tea", 60), ("pasta", 600), ("egg", 360)] {
+        server.post_json(
+            &format!("/timer/{name}/start"),
+            &format!(r#"{{"seconds": {seconds}}}"#),
+        );
+    }
+
+    let out = stdout(&server.timer(&["list", "--json"]));
+    let listed: serde_json::Value = serde_json::from_str(&out).expect("one valid JSON document");
+    let listed = listed.as_array().expect("a top-level array");
+    let mut names: Vec<&str> = listed
+        .iter()
+        .map(|timer| timer["name"].as_str().expect("each entry has a name"))
+        .collect();
+    names.sort_unstable();
+    assert_eq!(names, ["default", "egg", "pasta", "tea"]);
+    let tea = listed.iter().find(|timer| timer["name"] == "tea").unwrap();
+    assert_eq!(tea["original_duration_secs"], 60);
+
+    let out = stdout(&server.timer(&["list", "--ndjson"]));
+    let lines: Vec<serde_json::Value> = out
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("each line is JSON"))
+        .collect();
+    assert_eq!(lines.len(), 4);
+}
+
+/// The `expires_at=HH:MM:SS` suffix as seconds into the day.
+fn expiry_secs(out: &str) -> i64 {
+    let hms = out
+        .split_whitespace()
+        .find_map(|field| field.strip_prefix("expires_at="))
+        .unwrap_or_else(|| panic!("no expiry in {out:?}"));
+    hms.split(':')
+        .map(|part| part.parse::<i64>().expect("numeric time"))
+        .fold(0, |secs, part| secs * 60 + part)
+}
+
+#[test]
+fn tz_formats_wall_clock_times_in_that_zone() {
+    let server = Server::start(&[]);
+    server.post_json("/start", r#"{"seconds": 3600}"#);
+
+    let expiry_in = |tz: &str| {
+        expiry_secs(&stdout(&server.timer(&[
+            "--tz",
+            tz,
+            "status",
+            "--show-expiry",
+        ])))
+    };
+    let utc = expiry_in("UTC");
+    for (tz, offset_secs) in [("Asia/Tokyo", 9 * 3600), ("Asia/Kolkata", 5 * 3600 + 1800)] {
+        let local = expiry_in(tz);
+        let shift = (local - utc).rem_euclid(24 * 3600);
+        assert!(
+            (offset_secs - 2..=offset_secs + 2).contains(&shift),
+            "{tz}: {local} vs UTC {utc}"
+        );
+    }
+
+    let output = server.timer(&["--tz", "Mars/Olympus_Mons", "status"]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn egg_soft_starts_the_egg_timer_for_six_minutes() {
+    let server = Server::start(&[]);
+    stdout(&server.timer(&["egg", "soft"]));
+
+    let egg = server.get_json("/timer/egg/time_left");
+    assert_eq!(egg["original_duration_secs"], 360);
+    assert_eq!(egg["running"], true);
+    let timers = server.get_json("/timers");
+    assert_eq!(timers["default"]["original_duration_secs"], 0);
+}
+
+#[test]
+fn wide_hours_warning_is_only_for_the_plain_status_line() {
+    let server = Server::start(&[]);
+    stdout(&server.timer(&["start", "--no-warn", "400h"]));
+    let stderr = |args: &[&str]| {
+        let output = server.timer(args);
+        stdout(&output);
+        String::from_utf8(output.stderr).expect("utf-8 output")
+    };
+
+    assert!(stderr(&["status"]).contains("> 2 digit hours"));
+    assert_eq!(stderr(&["status", "--no-warn"]), "");
+    for mode in [
+        &["status", "--json"][..],
+        &["status", "--ps1"],
+        &["status", "--count-down"],
+        &["status", "--machine"],
+        &["status", "--quiet"],
+        &["status", "--json", "default"],
+    ] {
+        assert_eq!(stderr(mode), "", "{mode:?}");
+    }
+}
+
+#[test]
+fn closing_the_reader_mid_write_exits_cleanly() {
+    let server = Server::start(&[]);
+    // Far more output than a pipe buffers, so the reader leaves mid-write.
+    std::thread::scope(|scope| {
+        for first in 0..8 {
+            let server = &server;
+            scope.spawn(move || {
+                for i in (first..1000).step_by(8) {
+                    server.post_json(
+                        &format!("/timer/a-rather-long-timer-name-{i:04}/start"),
+                        r#"{"seconds": 600}"#,
+                    );
+                }
+            });
+        }
+    });
+
+    let fifo = server.dir.path().join("out.fifo");
+    let path = CString::new(fifo.as_os_str().as_bytes()).expect("no NUL in path");
+    // SAFETY: `path` is a valid NUL-terminated string.
+    assert_eq!(unsafe { libc::mkfifo(path.as_ptr(), 0o600) }, 0);
+    let reader = std::thread::spawn({
+        let fifo = fifo.clone();
+        move || {
+            let mut head = [0; 100];
+            File::open(fifo)
+                .and_then(|mut file| file.read_exact(&mut head))
+                .expect("failed to read from the FIFO");
+        }
+    });
+    let writer = OpenOptions::new()
+        .write(true)
+        .open(&fifo)
+        .expect("failed to open the FIFO");
+
+    let output = common::timer_command(&server.socket, &server.dir)
+        .arg("list")
+        .stdout(writer)
+        .output()
+        .expect("failed to run timer");
+    reader.join().expect("reader thread panicked");
+    assert!(output.status.success(), "exited with {}", output.status);
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+}
+
+#[test]
+fn status_rejects_names_that_would_break_the_query() {
+    let server = Server::start(&[]);
+    for name in ["my tea", "tea&missing=include", "tea#x", "tea,pasta", ""] {
+        let output = server.timer(&["status", "--json", name]);
+        assert_eq!(output.status.code(), Some(2), "{name:?}");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("label"), "{name:?}: {stderr}");
+    }
+    stdout(&server.timer(&["status", "--json", "tea", "pasta.v2"]));
+}
+
+#[test]
+fn output_file_respects_format() {
+    let server = Server::start(&[]);
+    server.post_json("/start", r#"{"seconds": 300}"#);
+    let secs = server.post_json("/pause", "")["time_left_secs"].clone();
+    let file = server.dir.path().join("status");
+    let file_arg = file.to_str().expect("utf-8 path");
+
+    let out = stdout(&server.timer(&[
+        "status",
+        "--format",
+        "json-compact-secs",
+        "--output-file",
+        file_arg,
+    ]));
+    assert_eq!(out, "");
+    let written = std::fs::read_to_string(&file).expect("output file exists");
+    assert_eq!(written, format!("{{\"s\":{secs},\"r\":false}}\n"));
+
+    let template = server.dir.path().join("status.tmpl");
+    std::fs::write(&template, "{time_left_secs} left\n").expect("write template");
+    let format = format!("@{}", template.display());
+    let printed = stdout(&server.timer(&["status", "--format", &format]));
+    stdout(&server.timer(&["status", "--format", &format, "--output-file", file_arg]));
+    let written = std::fs::read_to_string(&file).expect("output file exists");
+    assert_eq!(written, printed);
+    assert_eq!(written, format!("{secs} left\n"));
+}
@@ -0,0 +1,220 @@
+//! Starts real `timersock` processes on throwaway sockets and talks to them
+//! over plain HTTP/1.1, or through the `timer` CLI.
+
+#![allow(dead_code)]
+
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Output, Stdio};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+static NEXT_DIR: AtomicU32 = AtomicU32::new(0);
+
+/// A directory under the system temp dir, removed again on drop.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new() -> Self {
+        let path = std::env::temp_dir().join(format!(
+            "timersock-test-{}-{}",
+            std::process::id(),
+            NEXT_DIR.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::create_dir_all(&path).expect("failed to create temp dir");
+        Self(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+pub struct Server {
+    child: Child,
+    pub socket: PathBuf,
+    pub dir: TempDir,
+}
+
+impl Server {
+    pub fn start(args: &[&str]) -> Self {
+        Self::start_in(TempDir::new(), args)
+    }
+
+    /// Starts the server in `dir`, which holds the socket and outlives it.
+    pub fn start_in(dir: TempDir, args: &[&str]) -> Self {
+        let socket = dir.path().join("timer.sock");
+        let child = Command::new(env!("CARGO_BIN_EXE_timersock"))
+            .arg("--socket")
+            .arg(&socket)
+            .args(args)
+            .env_remove("TIMER_SOCK")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("failed to spawn timersock");
+        let server = Self { child, socket, dir };
+        server.wait_until_ready();
+        server
+    }
+
+    fn wait_until_ready(&self) {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while UnixStream::connect(&self.socket).is_err() {
+            assert!(Instant::now() < deadline, "timersock did not start");
+            thread::sleep(Duration::from_millis(20));
+        }
+    }
+
+    pub fn pid(&self) -> u32 {
+        self.child.id()
+    }
+
+    /// Sends SIGTERM and waits for a graceful exit.
+    pub fn terminate(&mut self) {
+        // SAFETY: signalling our own child, which has not been reaped yet.
+        unsafe { libc::kill(self.child.id() as libc::pid_t, libc::SIGTERM) };
+        let status = self.child.wait().expect("failed to wait for timersock");
+        assert!(status.success(), "timersock exited with {status}");
+    }
+
+    pub fn request(&self, method: &str, path: &str, body: Option<&str>) -> Response {
+        request(&self.socket, method, path, body)
+    }
+
+    pub fn get_json(&self, path: &str) -> serde_json::Value {
+        let response = self.request("GET", path, None);
+        assert_eq!(response.status, 200, "GET {path}: {}", response.body);
+        response.json()
+    }
+
+    pub fn post_json(&self, path: &str, body: &str) -> serde_json::Value {
+        let response = self.request("POST", path, Some(body));
+        assert_eq!(response.status, 200, "POST {path}: {}", response.body);
+        response.json()
+    }
+
+    /// Runs the `timer` CLI against this server.
+    pub fn timer(&self, args: &[&str]) -> Output {
+        timer_command(&self.socket, &self.dir)
+            .args(args)
+            .output()
+            .expect("failed to run timer")
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// A `timer` command pointed at `socket`, isolated from the caller's
+/// environment and cache.
+pub fn timer_command(socket: &Path, dir: &TempDir) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_timer"));
+    command
+        .arg("--socket")
+        .arg(socket)
+        .env("XDG_CACHE_HOME", dir.path().join("cache"))
+        .env("HOME", dir.path())
+        .env("TZ", "UTC")
+        .env_remove("TIMER_SOCK")
+        .env_remove("TIMER_UNIT")
+        .env_remove("TIMER_NO_CLOBBER")
+        .env_remove("LOW_TIME_THRESHOLD_SECS")
+        .env_remove("TIMER_EGG_SOFT")
+        .env_remove("TIMER_EGG_MEDIUM")
+        .env_remove("TIMER_EGG_HARD");
+    command
+}
+
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Response {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn json(&self) -> serde_json::Value {
+        serde_json::from_str(&self.body)
+            .unwrap_or_else(|err| panic!("invalid JSON {:?}: {err}", self.body))
+    }
+}
+
+pub fn request(socket: &Path, method: &str, path: &str, body: Option<&str>) -> Response {
+    let mut raw = format!("{method} {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n");
+    if let Some(body) = body {
+        raw.push_str(&format!(
+            "Content-Type: application/json\r\nContent-Length: {}\r\n",
+            body.len()
+        ));
+    }
+    raw.push_str("\r\n");
+    raw.push_str(body.unwrap_or_default());
+    send_raw(socket, raw.as_bytes()).expect("connection closed without a response")
+}
+
+/// Writes `raw` as-is and parses the reply; `None` if the server closed the
+/// connection without answering.
+pub fn send_raw(socket: &Path, raw: &[u8]) -> Option<Response> {
+    let mut stream = UnixStream::connect(socket).expect("failed to connect to timersock");
+    stream
+        .set_read_timeout(Some(Duration::from_secs(10)))
+        .expect("failed to set read timeout");
+    // The server may answer and hang up before reading everything we send.
+    let _ = stream.write_all(raw);
+    let mut reply = Vec::new();
+    let _ = stream.read_to_end(&mut reply);
+    if reply.is_empty() {
+        return None;
+    }
+
+    let reply = String::from_utf8_lossy(&reply);
+    let (head, body) = reply.split_once("\r\n\r\n").expect("malformed response");
+    let mut lines = head.lines();
+    let status = lines
+        .next()
+        .and_then(|line| line.split(' ').nth(1))
+        .and_then(|code| code.parse().ok())
+        .expect("malformed status line");
+    let headers = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .collect();
+    Some(Response {
+        status,
+        headers,
+        body: body.to_string(),
+    })
+}
+
+/// Polls `check` until it holds or `timeout` runs out.
+pub fn eventually(timeout: Duration, mut check: impl FnMut() -> bool) -> bool {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if check() {
+            return true;
+        }
+        if Instant::now() >= deadline {
+            return false;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+}
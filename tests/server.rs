@@ -0,0 +1,368 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{Server, eventually, send_raw};
+
+#[test]
+fn mirror_replays_mutations_to_the_peer() {
+    let mirror = Server::start(&[]);
+    let mirror_socket = mirror.socket.to_str().expect("utf-8 socket path");
+    let primary = Server::start(&["--mirror", mirror_socket]);
+
+    let started = primary.post_json("/timer/tea/start", r#"{"seconds": 300}"#);
+    assert_eq!(started["original_duration_secs"], 300);
+
+    let replayed = eventually(Duration::from_secs(5), || {
+        let response = mirror.request("GET", "/timer/tea/time_left", None);
+        response.status == 200 && response.json()["original_duration_secs"] == 300
+    });
+    assert!(replayed, "the mirror never saw the start");
+
+    let primary_snapshot = primary.get_json("/timer/tea/time_left");
+    let mirror_snapshot = mirror.get_json("/timer/tea/time_left");
+    for field in ["running", "original_duration_secs", "completed"] {
+        assert_eq!(primary_snapshot[field], mirror_snapshot[field], "{field}");
+    }
+}
+
+#[test]
+fn multi_poll_reports_present_names_and_optionally_missing_ones() {
+    let server = Server::start(&[]);
+    server.post_json("/timer/tea/start", r#"{"seconds": 60}"#);
+    server.post_json("/timer/pasta/start", r#"{"seconds": 600}"#);
+
+    let polled = server.get_json("/time_left?names=tea,nope,pasta");
+    let names: Vec<&String> = polled.as_object().expect("object").keys().collect();
+    assert_eq!(names, ["pasta", "tea"]);
+    assert_eq!(polled["tea"]["original_duration_secs"], 60);
+    assert_eq!(polled["pasta"]["original_duration_secs"], 600);
+
+    let polled = server.get_json("/time_left?names=tea,nope,pasta&missing=include");
+    assert_eq!(polled["nope"], serde_json::json!({ "missing": true }));
+    assert_eq!(polled["tea"]["original_duration_secs"], 60);
+
+    let encoded = server.get_json("/time_left?names=te%61%2Cpasta");
+    assert_eq!(encoded["tea"]["original_duration_secs"], 60);
+    assert_eq!(encoded["pasta"]["original_duration_secs"], 600);
+
+    let response = server.request("GET", "/time_left?names=tea%2", None);
+    assert_eq!(response.status, 400);
+}
+
+fn completions(server: &Server) -> u64 {
+    server.get_json("/debug/stats")["completions_total"]
+        .as_u64()
+        .expect("completions_total")
+}
+
+#[test]
+fn completion_is_reported_with_no_client_connected() {
+    let server = Server::start(&["--stats"]);
+    server.post_json("/start", r#"{"seconds": 1}"#);
+
+    // Nobody talks to the server while the timer runs out.
+    std::thread::sleep(Duration::from_millis(2500));
+    assert_eq!(completions(&server), 1);
+}
+
+#[test]
+fn restart_right_after_completion_still_reports_it() {
+    let server = Server::start(&["--stats"]);
+    server.post_json("/start", r#"{"seconds": 0}"#);
+    server.post_json("/start", r#"{"seconds": 60}"#);
+
+    assert!(eventually(Duration::from_secs(3), || completions(&server) == 1));
+    std::thread::sleep(Duration::from_millis(1500));
+    assert_eq!(completions(&server), 1);
+}
+
+#[test]
+fn zero_second_start_completes_and_fires_the_hook() {
+    let server = Server::start(&["--stats"]);
+    let snapshot = server.post_json("/start", r#"{"seconds": 0}"#);
+    assert_eq!(snapshot["completed"], true);
+    assert_eq!(snapshot["running"], false);
+    assert_eq!(snapshot["time_left_secs"], 0);
+    assert!(snapshot["completed_at_unix"].is_u64());
+
+    assert!(eventually(Duration::from_secs(3), || completions(&server) == 1));
+}
+
+#[test]
+fn server_starts_with_a_custom_backlog() {
+    let server = Server::start(&["--backlog", "16"]);
+    assert_eq!(server.get_json("/config")["backlog"], 16);
+    assert_eq!(server.get_json("/healthz")["status"], "ok");
+}
+
+#[test]
+fn message_reads_back_what_was_set() {
+    let server = Server::start(&[]);
+    server.post_json("/start", r#"{"seconds": 60}"#);
+
+    let snapshot = server.post_json("/message", r#"{"message": "Tea is ready"}"#);
+    assert_eq!(snapshot["message"], "Tea is ready");
+    assert_eq!(server.get_json("/message")["message"], "Tea is ready");
+
+    // The message survives a restart of the timer.
+    server.post_json("/start", r#"{"seconds": 30}"#);
+    assert_eq!(server.get_json("/message")["message"], "Tea is ready");
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .expect("clock after the epoch")
+        .as_secs()
+}
+
+#[test]
+fn cancelled_schedule_does_not_fire() {
+    let server = Server::start(&[]);
+    server.post_json("/start", r#"{"seconds": 60}"#);
+    let at_unix = unix_now() + 2;
+    server.post_json(
+        "/schedule",
+        &format!(r#"{{"action": "pause", "at_unix": {at_unix}}}"#),
+    );
+
+    // Still inside the delay window.
+    let cancelled = server.post_json("/cancel_schedule", r#"{"action": "pause"}"#);
+    assert_eq!(cancelled["cancelled"].as_array().map(Vec::len), Some(1));
+    assert_eq!(server.get_json("/schedule"), serde_json::json!([]));
+
+    std::thread::sleep(Duration::from_secs(3));
+    assert_eq!(server.get_json("/time_left")["running"], true);
+}
+
+#[test]
+fn concurrent_starts_and_reads_leave_a_consistent_timer() {
+    let server = Server::start(&[]);
+    let durations: Vec<u64> = (0..32).map(|i| 1000 + i * 10).collect();
+
+    std::thread::scope(|scope| {
+        for &seconds in &durations {
+            let (server, durations) = (&server, &durations);
+            scope.spawn(move || {
+                let started =
+                    server.post_json("/timer/race/start", &format!(r#"{{"seconds": {seconds}}}"#));
+                assert_eq!(started["original_duration_secs"], seconds);
+                for _ in 0..5 {
+                    let read = server.get_json("/timer/race/time_left");
+                    let original = read["original_duration_secs"].as_u64().unwrap();
+                    let left = read["time_left_secs"].as_u64().unwrap();
+                    assert!(durations.contains(&original), "{read}");
+                    assert!(left <= original, "{read}");
+                }
+            });
+        }
+    });
+
+    let last = server.get_json("/timer/race/time_left");
+    let original = last["original_duration_secs"].as_u64().unwrap();
+    let left = last["time_left_secs"].as_u64().unwrap();
+    assert!(durations.contains(&original), "{last}");
+    assert!(original - left <= 2, "{last}");
+    assert_eq!(last["running"], true);
+    assert_eq!(last["completed"], false);
+    let timers = server.get_json("/timers");
+    assert_eq!(timers.as_object().map(|timers| timers.len()), Some(2));
+}
+
+/// Oversized and repeated headers must be refused, 431 or a hang-up, without
+/// taking the server down.
+#[test]
+fn header_floods_are_refused_and_the_server_keeps_serving() {
+    let server = Server::start(&[]);
+
+    let huge = format!(
+        "GET /healthz HTTP/1.1\r\nHost: localhost\r\nX-Flood: {}\r\n\r\n",
+        "a".repeat(256 * 1024)
+    );
+    let mut repeated = String::from("GET /healthz HTTP/1.1\r\nHost: localhost\r\n");
+    for i in 0..10_000 {
+        repeated.push_str(&format!("X-Flood-{i}: {}\r\n", "b".repeat(16)));
+    }
+    repeated.push_str("\r\n");
+
+    for flood in [huge, repeated] {
+        if let Some(response) = send_raw(&server.socket, flood.as_bytes()) {
+            assert_eq!(response.status, 431, "{}", response.body);
+        }
+        assert_eq!(server.get_json("/healthz")["status"], "ok");
+    }
+}
+
+#[test]
+fn random_header_garbage_does_not_take_the_server_down() {
+    let server = Server::start(&[]);
+    // A fixed-seed xorshift keeps failures reproducible.
+    let mut seed: u64 = 0x9e37_79b9_7f4a_7c15;
+    let mut next = move || {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        seed
+    };
+
+    for _ in 0..50 {
+        let len = (next() % (128 * 1024)) as usize;
+        let mut flood = b"GET /healthz HTTP/1.1\r\nHost: localhost\r\n".to_vec();
+        flood.extend((0..len).map(|_| match next() % 8 {
+            0 => b'\r',
+            1 => b'\n',
+            2 => b':',
+            _ => (next() % 256) as u8,
+        }));
+        flood.extend_from_slice(b"\r\n\r\n");
+        let _ = send_raw(&server.socket, &flood);
+    }
+    assert_eq!(server.get_json("/healthz")["status"], "ok");
+}
+
+#[test]
+fn every_response_carries_a_numeric_response_time() {
+    let server = Server::start(&[]);
+    let responses = [
+        server.request("GET", "/time_left", None),
+        server.request("POST", "/start", Some(r#"{"seconds": 60}"#)),
+        server.request("HEAD", "/healthz", None),
+        server.request("DELETE", "/time_left", None),
+        server.request("GET", "/nope", None),
+        server.request("GET", "/timer//time_left", None),
+        server.request("GET", "/timer/bad%zz/time_left", None),
+    ];
+    for response in responses {
+        let value = response
+            .header("x-response-time-ms")
+            .unwrap_or_else(|| panic!("no response time on a {} response", response.status));
+        let millis: f64 = value.parse().expect("response time is a number");
+        assert!(millis >= 0.0);
+        assert_eq!(
+            value.split_once('.').map(|(_, decimals)| decimals.len()),
+            Some(3)
+        );
+    }
+}
+
+#[test]
+fn pid_file_is_written_on_start_and_removed_on_stop() {
+    let dir = common::TempDir::new();
+    let pid_file = dir.path().join("timersock.pid");
+    let mut server = Server::start_in(dir, &["--pid-file", pid_file.to_str().unwrap()]);
+
+    let written = std::fs::read_to_string(&pid_file).expect("pid file exists");
+    assert_eq!(written.trim(), server.pid().to_string());
+
+    server.terminate();
+    assert!(server.dir.path().exists());
+    assert!(!pid_file.exists(), "pid file left behind");
+    assert!(!server.socket.exists(), "socket left behind");
+}
+
+#[test]
+fn extending_a_finished_timer_leaves_it_stopped_by_default() {
+    let server = Server::start(&[]);
+    server.post_json("/start", r#"{"seconds": 0}"#);
+
+    let extended = server.post_json("/extend", r#"{"seconds": 60}"#);
+    assert_eq!(extended["time_left_secs"], 60);
+    assert_eq!(extended["running"], false);
+    assert_eq!(extended["completed"], false);
+}
+
+#[test]
+fn extending_a_finished_timer_with_resume_restarts_it() {
+    let server = Server::start(&[]);
+    server.post_json("/start", r#"{"seconds": 0}"#);
+
+    let extended = server.post_json("/extend", r#"{"seconds": 60, "resume": true}"#);
+    assert_eq!(extended["time_left_secs"], 60);
+    assert_eq!(extended["running"], true);
+
+    // A timer that still has time left is not affected by the flag.
+    server.post_json("/pause", "");
+    let extended = server.post_json("/extend", r#"{"seconds": 60, "resume": true}"#);
+    assert_eq!(extended["running"], false);
+}
+
+fn get_with(server: &Server, headers_and_body: &str) -> u16 {
+    let raw = format!(
+        "GET /healthz HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n{headers_and_body}"
+    );
+    send_raw(&server.socket, raw.as_bytes())
+        .expect("connection closed without a response")
+        .status
+}
+
+#[test]
+fn strict_methods_rejects_get_bodies_but_not_empty_ones() {
+    let server = Server::start(&["--strict-methods"]);
+    assert_eq!(get_with(&server, "Content-Length: 2\r\n\r\n{}"), 400);
+    assert_eq!(
+        get_with(
+            &server,
+            "Transfer-Encoding: chunked\r\n\r\n2\r\n{}\r\n0\r\n\r\n"
+        ),
+        400
+    );
+    assert_eq!(get_with(&server, "Content-Length: 0\r\n\r\n"), 200);
+    assert_eq!(
+        get_with(&server, "Transfer-Encoding: chunked\r\n\r\n0\r\n\r\n"),
+        200
+    );
+    assert_eq!(get_with(&server, "\r\n"), 200);
+}
+
+#[test]
+fn get_bodies_are_ignored_by_default() {
+    let server = Server::start(&[]);
+    assert_eq!(get_with(&server, "Content-Length: 2\r\n\r\n{}"), 200);
+    assert_eq!(
+        get_with(
+            &server,
+            "Transfer-Encoding: chunked\r\n\r\n2\r\n{}\r\n0\r\n\r\n"
+        ),
+        200
+    );
+}
+
+#[test]
+fn overtime_keeps_counting_past_zero() {
+    let server = Server::start(&["--allow-overtime"]);
+    server.post_json("/start", r#"{"seconds": 1}"#);
+
+    std::thread::sleep(Duration::from_millis(2500));
+    let overdue = server.get_json("/time_left");
+    assert_eq!(overdue["time_left_secs"], 0);
+    assert_eq!(overdue["running"], true);
+    assert_eq!(overdue["completed"], true);
+    let first = overdue["overtime_secs"].as_u64().expect("overtime_secs");
+    assert!(first > 0, "{overdue}");
+
+    std::thread::sleep(Duration::from_millis(2000));
+    let later = server.get_json("/time_left");
+    assert_eq!(later["time_left_secs"], 0);
+    assert!(
+        later["overtime_secs"].as_u64().expect("overtime_secs") > first,
+        "{later}"
+    );
+}
+
+#[test]
+fn busy_responses_carry_a_response_time() {
+    let server = Server::start(&["--max-connections", "1", "--queue-timeout", "100"]);
+    // A half-sent request keeps the only slot taken.
+    let mut holder = std::os::unix::net::UnixStream::connect(&server.socket).expect("connect");
+    std::io::Write::write_all(&mut holder, b"GET /healthz HTTP/1.1\r\n").expect("write");
+
+    let response = server.request("GET", "/healthz", None);
+    assert_eq!(response.status, 503, "{}", response.body);
+    let millis: f64 = response
+        .header("x-response-time-ms")
+        .expect("no response time on the 503")
+        .parse()
+        .expect("response time is a number");
+    assert!(millis >= 0.0);
+}